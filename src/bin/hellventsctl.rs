@@ -0,0 +1,111 @@
+use std::{env, error::Error, net::SocketAddr, path::PathBuf, process};
+
+use clap::Parser;
+use hellvents::{
+    config::load_config,
+    manage::{
+        command::ChatSubcommand,
+        control::{ControlRequest, ControlResponse},
+    },
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+/// Control a running hellvents instance. Commands are the same as after `/hv` in chat.
+#[derive(Parser)]
+#[command(name = "hellventsctl", version, about, disable_help_subcommand = true)]
+struct CtlConfig {
+    /// The config of the hellvents instance, the address and token are taken from its
+    /// control section.
+    #[clap(long, short, default_value = "config.toml")]
+    config: PathBuf,
+
+    /// The address of the control interface, overrides the config.
+    #[clap(long, short)]
+    address: Option<SocketAddr>,
+
+    /// The token of the control interface, overrides the config.
+    #[clap(long, short)]
+    token: Option<String>,
+
+    #[command(subcommand)]
+    command: ChatSubcommand,
+}
+
+/// Options of hellventsctl which are followed by a value.
+const VALUE_OPTIONS: &[&str] = &["--config", "-c", "--address", "-a", "--token", "-t"];
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    // Parsing validates the command and handles help before anything is sent.
+    let cli = CtlConfig::parse();
+
+    let (address, token) = match (cli.address, cli.token) {
+        (Some(address), Some(token)) => (address, token),
+        (address, token) => {
+            let control = load_config(&cli.config)?.control;
+            let Some(token) = token.or(control.token) else {
+                eprintln!(
+                    "The control interface is disabled, set a token in {}",
+                    cli.config.display()
+                );
+                process::exit(2);
+            };
+            (address.unwrap_or(control.address), token)
+        }
+    };
+
+    let request = ControlRequest {
+        token,
+        args: command_args(env::args().skip(1)),
+    };
+
+    let response = send(address, &request).await?;
+    println!("{}", response.replies.join("\n\n"));
+    if !response.ok {
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Drop the options of hellventsctl which precede the command, the rest is sent as is.
+fn command_args(mut args: impl Iterator<Item = String>) -> Vec<String> {
+    let mut command = Vec::new();
+    while let Some(arg) = args.next() {
+        if VALUE_OPTIONS.contains(&arg.as_str()) {
+            args.next();
+            continue;
+        }
+
+        if arg.starts_with('-') {
+            continue;
+        }
+
+        command.push(arg);
+        break;
+    }
+
+    command.extend(args);
+    command
+}
+
+async fn send(
+    address: SocketAddr,
+    request: &ControlRequest,
+) -> Result<ControlResponse, Box<dyn Error>> {
+    let stream = TcpStream::connect(address).await?;
+    let (read, mut write) = stream.into_split();
+
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    write.write_all(line.as_bytes()).await?;
+
+    let Some(line) = BufReader::new(read).lines().next_line().await? else {
+        return Err("Hellvents closed the connection without responding".into());
+    };
+
+    Ok(serde_json::from_str(&line)?)
+}
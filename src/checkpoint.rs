@@ -0,0 +1,286 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use wise_api::rcon::parsing::Player;
+
+use crate::manage::command::StartEvent;
+
+/// Configures where the state of the running events is kept so it survives restarts.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CheckpointConfig {
+    #[serde(default = "default_path")]
+    pub path: PathBuf,
+
+    /// Whether interrupted events continue after a restart or are ended right away.
+    #[serde(default = "default_resume")]
+    pub resume: bool,
+}
+
+fn default_path() -> PathBuf {
+    PathBuf::from("hellvents_checkpoint.json")
+}
+
+fn default_resume() -> bool {
+    true
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        Self {
+            path: default_path(),
+            resume: default_resume(),
+        }
+    }
+}
+
+/// Everything needed to pick an event back up after a restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The history run of the event.
+    pub run: String,
+    /// When the event was first started.
+    pub started: DateTime<Utc>,
+    pub config: StartEvent,
+    pub state: EventState,
+}
+
+/// The event specific part of a [`Checkpoint`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum EventState {
+    /// The event has nothing worth keeping.
+    Stateless,
+    MeleeMania {
+        /// Whether the start has been announced to the players.
+        started: bool,
+        end: DateTime<Utc>,
+        /// Whether the event was waiting for the next match.
+        paused: bool,
+        /// Every player with their count and when they last committed an infraction or had
+        /// one forgiven.
+        infractions: Vec<(Player, u32, DateTime<Utc>)>,
+    },
+    SkyEye {
+        /// Players holding admin cam access with the team they were granted it for.
+        camers: Vec<(Player, String)>,
+        /// Whether the event was waiting for the next match.
+        paused: bool,
+    },
+}
+
+/// Stores the checkpoints of all running events in a single file.
+#[derive(Debug, Clone)]
+pub struct Checkpoints {
+    path: PathBuf,
+
+    /// The checkpoints in the file by the history run of their event.
+    saved: Arc<Mutex<HashMap<String, Checkpoint>>>,
+
+    /// Set once all checkpoints have been discarded, nothing is saved afterwards.
+    discarded: Arc<AtomicBool>,
+}
+
+impl Checkpoints {
+    pub fn new(config: &CheckpointConfig) -> Self {
+        Self {
+            path: config.path.clone(),
+            saved: Arc::default(),
+            discarded: Arc::default(),
+        }
+    }
+
+    /// Read the checkpoints left behind by a previous run of hellvents.
+    pub async fn load(&self) -> io::Result<Vec<Checkpoint>> {
+        let content = match tokio::fs::read_to_string(&self.path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let checkpoints: Vec<Checkpoint> = serde_json::from_str(&content)?;
+
+        let mut guard = self.saved.lock().await;
+        for checkpoint in &checkpoints {
+            guard.insert(checkpoint.run.clone(), checkpoint.clone());
+        }
+
+        Ok(checkpoints)
+    }
+
+    /// Replace the checkpoint of an event.
+    pub async fn save(&self, checkpoint: &Checkpoint) {
+        let mut guard = self.saved.lock().await;
+        if self.discarded.load(Ordering::Relaxed) {
+            return;
+        }
+        guard.insert(checkpoint.run.clone(), checkpoint.clone());
+        self.write(&guard).await;
+    }
+
+    /// Remove the checkpoint of the event with the given history run.
+    pub async fn clear(&self, run: &str) {
+        let mut guard = self.saved.lock().await;
+        guard.remove(run);
+
+        if !guard.is_empty() {
+            self.write(&guard).await;
+            return;
+        }
+
+        match tokio::fs::remove_file(&self.path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to clear checkpoint: {}", e),
+        }
+    }
+
+    /// Remove the checkpoints of all events for good, so none of them is resumed after a
+    /// restart. Events still running cannot save new ones afterwards.
+    pub async fn discard(&self) {
+        let mut guard = self.saved.lock().await;
+        self.discarded.store(true, Ordering::Relaxed);
+        guard.clear();
+
+        match tokio::fs::remove_file(&self.path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to discard checkpoints: {}", e),
+        }
+    }
+
+    /// Replace the file. It is written next to the old one first so a crash while writing
+    /// never leaves a broken checkpoint behind.
+    async fn write(&self, checkpoints: &HashMap<String, Checkpoint>) {
+        let temporary = self.path.with_extension("tmp");
+        let result = async {
+            let checkpoints: Vec<&Checkpoint> = checkpoints.values().collect();
+            let content = serde_json::to_vec(&checkpoints)?;
+            tokio::fs::write(&temporary, content).await?;
+            tokio::fs::rename(&temporary, &self.path).await
+        };
+
+        if let Err(e) = result.await {
+            warn!("Failed to save checkpoint: {}", e);
+        }
+    }
+}
+
+/// Convert an [`Instant`] into wall clock time so it can be stored across restarts.
+pub fn to_wall_clock(instant: Instant) -> DateTime<Utc> {
+    let now = Instant::now();
+    let offset = match instant.checked_duration_since(now) {
+        Some(ahead) => TimeDelta::from_std(ahead),
+        None => TimeDelta::from_std(now.duration_since(instant)).map(|behind| -behind),
+    };
+
+    Utc::now() + offset.unwrap_or_default()
+}
+
+/// Convert stored wall clock time back into an [`Instant`], times in the past become now.
+pub fn from_wall_clock(time: DateTime<Utc>) -> Instant {
+    let ahead = (time - Utc::now()).to_std().unwrap_or(Duration::ZERO);
+    Instant::now() + ahead
+}
+
+/// Convert stored wall clock time in the past back into an [`Instant`] without moving it to
+/// now. Times before the monotonic clock started, such as before a reboot, are moved forward
+/// until the clock can represent them.
+pub fn from_past_wall_clock(time: DateTime<Utc>) -> Instant {
+    let now = Instant::now();
+    let mut behind = (Utc::now() - time).to_std().unwrap_or(Duration::ZERO);
+    loop {
+        if let Some(instant) = now.checked_sub(behind) {
+            return instant;
+        }
+        behind /= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wall_clock_round_trip() {
+        let ahead = Instant::now() + Duration::from_secs(90);
+
+        let restored = from_wall_clock(to_wall_clock(ahead));
+
+        let drift = restored
+            .checked_duration_since(ahead)
+            .unwrap_or_else(|| ahead.duration_since(restored));
+        assert!(drift < Duration::from_secs(1), "drifted by {:?}", drift);
+    }
+
+    #[test]
+    fn wall_clock_in_past_becomes_now() {
+        let before = Instant::now();
+        let past = Utc::now() - TimeDelta::minutes(5);
+
+        let restored = from_wall_clock(past);
+
+        assert!(restored >= before);
+        assert!(restored <= Instant::now());
+    }
+
+    #[test]
+    fn past_wall_clock_stays_in_past() {
+        let past = Instant::now() - Duration::from_secs(300);
+
+        let restored = from_past_wall_clock(to_wall_clock(past));
+
+        let drift = restored
+            .checked_duration_since(past)
+            .unwrap_or_else(|| past.duration_since(restored));
+        assert!(drift < Duration::from_secs(1), "drifted by {:?}", drift);
+    }
+
+    #[test]
+    fn parses_checkpoint_list() {
+        let content = r#"[{
+            "run": "current",
+            "started": "2024-05-01T20:00:00Z",
+            "config": { "RadioSpies": {} },
+            "state": { "event": "stateless" }
+        }]"#;
+
+        let checkpoints: Vec<Checkpoint> = serde_json::from_str(content).unwrap();
+
+        assert_eq!(checkpoints.len(), 1);
+        assert_eq!(checkpoints[0].run, "current");
+    }
+
+    #[tokio::test]
+    async fn discarded_checkpoints_stay_gone() {
+        let path = std::env::temp_dir().join(format!("hellvents_{}.json", uuid::Uuid::new_v4()));
+        let checkpoints = Checkpoints::new(&CheckpointConfig {
+            path: path.clone(),
+            resume: true,
+        });
+        let checkpoint = Checkpoint {
+            run: "running".to_string(),
+            started: Utc::now(),
+            config: StartEvent::RadioSpies {},
+            state: EventState::Stateless,
+        };
+
+        checkpoints.save(&checkpoint).await;
+        assert!(path.exists());
+
+        checkpoints.discard().await;
+        checkpoints.save(&checkpoint).await;
+        assert!(!path.exists());
+    }
+}
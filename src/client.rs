@@ -1,22 +1,36 @@
 use std::{
+    collections::HashMap,
     error::Error,
-    time::{Duration, Instant},
+    fmt::Display,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
 };
 
 use futures_util::{
     stream::{SplitSink, SplitStream},
     SinkExt, StreamExt,
 };
-use log::{trace, warn};
+use log::{debug, info, trace, warn};
 use serde::Deserialize;
 use tokio::{
     net::TcpStream,
     sync::{
-        broadcast::{self, error::TryRecvError},
-        mpsc,
+        broadcast::{
+            self,
+            error::{RecvError, TryRecvError},
+        },
+        mpsc, oneshot,
     },
+    time::{sleep, timeout},
+};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{self, Message},
+    MaybeTlsStream, WebSocketStream,
 };
-use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
 use uuid::Uuid;
 use wise_api::{
     messages::{
@@ -26,12 +40,114 @@ use wise_api::{
     rcon::parsing::{playerinfo::PlayerInfo, Player, PlayerId},
 };
 
+use crate::game::GameState;
+
 pub type RawWsClient = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug)]
+pub enum ClientError {
+    /// The server did not accept the login token.
+    AuthRejected,
+
+    /// The connection to the server was lost. Requests sent before are lost with it, the
+    /// connection is restored in the background unless all transceivers are gone.
+    Disconnected,
+
+    /// The server did not respond to a request in time.
+    Timeout,
+
+    /// The server failed to execute the request.
+    ServerFailure,
+
+    /// The server responded with something other than what the request asked for.
+    UnexpectedResponse,
+
+    /// The transceiver fell behind and missed this many messages, receiving continues after
+    /// the gap.
+    Lagged(u64),
+
+    /// The argument cannot be put into a command without changing its meaning.
+    InvalidArgument(String),
+
+    /// A message could not be serialized or deserialized.
+    Serialization(serde_json::Error),
+
+    /// The underlying websocket failed.
+    WebSocket(Box<tungstenite::Error>),
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::AuthRejected => write!(f, "server rejected the login token"),
+            ClientError::Disconnected => write!(f, "disconnected from server"),
+            ClientError::Timeout => write!(f, "server did not respond in time"),
+            ClientError::ServerFailure => write!(f, "server failed to execute request"),
+            ClientError::UnexpectedResponse => write!(f, "server sent an unexpected response"),
+            ClientError::Lagged(count) => write!(f, "fell behind and missed {} messages", count),
+            ClientError::InvalidArgument(argument) => {
+                write!(f, "cannot send \"{}\" in a command", argument)
+            }
+            ClientError::Serialization(e) => write!(f, "failed to (de)serialize message: {}", e),
+            ClientError::WebSocket(e) => write!(f, "websocket error: {}", e),
+        }
+    }
+}
+
+impl Error for ClientError {}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Serialization(value)
+    }
+}
+
+impl From<tungstenite::Error> for ClientError {
+    fn from(value: tungstenite::Error) -> Self {
+        Self::WebSocket(Box::new(value))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct ClientConfig {
     pub address: String,
     pub token: String,
+
+    /// How many messages can be queued for sending to the server.
+    #[serde(default = "default_to_server_capacity")]
+    pub to_server_capacity: usize,
+
+    /// How many received messages are kept for each transceiver before it starts lagging.
+    #[serde(default = "default_to_client_capacity")]
+    pub to_client_capacity: usize,
+
+    /// How many seconds to wait for the response to a request.
+    #[serde(default = "default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+}
+
+fn default_to_server_capacity() -> usize {
+    64
+}
+
+fn default_to_client_capacity() -> usize {
+    1024
+}
+
+fn default_request_timeout_secs() -> u64 {
+    30
+}
+
+/// Requests awaiting their response from the server, keyed by request id.
+type PendingRequests = Arc<Mutex<HashMap<String, PendingRequest>>>;
+
+#[derive(Debug)]
+struct PendingRequest {
+    response_tx: oneshot::Sender<ServerWsResponse>,
+
+    /// Whether the request has been handed to the current connection. Its response is lost if
+    /// the connection drops, requests still queued are sent once reconnected.
+    sent: bool,
 }
 
 #[derive(Debug)]
@@ -41,6 +157,14 @@ pub struct WsTransceiver {
 
     /// Messages being received from the server.
     to_client: broadcast::Receiver<ServerWsMessage>,
+
+    /// Messages missed by all transceivers sharing the connection.
+    total_missed: Arc<AtomicU64>,
+
+    /// Requests the connection routes responses to instead of broadcasting them.
+    pending: PendingRequests,
+
+    request_timeout: Duration,
 }
 
 impl Clone for WsTransceiver {
@@ -48,219 +172,577 @@ impl Clone for WsTransceiver {
         Self {
             to_server: self.to_server.clone(),
             to_client: self.to_client.resubscribe(),
+            total_missed: self.total_missed.clone(),
+            pending: self.pending.clone(),
+            request_timeout: self.request_timeout,
         }
     }
 }
 
 impl WsTransceiver {
-    pub async fn connect(config: &ClientConfig) -> Result<Self, Box<dyn Error>> {
-        let (mut ws_client, _) = connect_async(&config.address).await?;
-        ws_client
-            .send(Message::Text(config.token.to_string()))
-            .await?;
-
-        let Some(Ok(message)) = ws_client.next().await else {
-            panic!("Server failed to respond to login token... Is the token valid?")
-        };
-
-        let server_message =
-            serde_json::from_str::<ServerWsMessage>(message.into_text()?.as_str())?;
-
-        if !matches!(server_message, ServerWsMessage::Authenticated) {
-            panic!("Server did not respond with immediate Authentication response");
-        }
-
-        Ok(Self::from_client(ws_client))
+    /// Connect and log in to wise. The connection is kept alive in the background and
+    /// reestablished whenever it drops, all clones of the transceiver stay usable throughout.
+    pub async fn connect(config: &ClientConfig) -> Result<Self, ClientError> {
+        let ws_client = login(config).await?;
+        Ok(Self::from_client(config.clone(), ws_client))
     }
 
-    /// Create a new transceiver from the given raw client.
-    pub fn from_client(raw: RawWsClient) -> Self {
-        let (to_server_tx, to_server_rx) = mpsc::channel(10);
-        let (to_client_tx, to_client_rx) = broadcast::channel(10);
-        let (sink, stream) = raw.split();
-
-        _ = tokio::spawn(async move {
-            _ = to_server_loop(sink, to_server_rx).await;
-        });
-
-        _ = tokio::spawn(async move {
-            _ = to_client_loop(stream, to_client_tx).await;
-        });
+    /// Create a new transceiver from the given raw client, `config` is used to reconnect.
+    pub fn from_client(config: ClientConfig, raw: RawWsClient) -> Self {
+        let (to_server_tx, to_server_rx) = mpsc::channel(config.to_server_capacity);
+        let (to_client_tx, to_client_rx) = broadcast::channel(config.to_client_capacity);
+        let pending = PendingRequests::default();
+        let request_timeout = Duration::from_secs(config.request_timeout_secs);
+
+        _ = tokio::spawn(connection_loop(
+            config,
+            raw,
+            to_server_rx,
+            to_client_tx,
+            pending.clone(),
+        ));
 
         Self {
             to_server: to_server_tx,
             to_client: to_client_rx,
+            total_missed: Arc::default(),
+            pending,
+            request_timeout,
         }
     }
 
     /// Send a [`ClientWsMessage`] to the server and return.
-    pub async fn send(&self, message: ClientWsMessage) {
+    pub async fn send(&self, message: ClientWsMessage) -> Result<(), ClientError> {
         self.to_server
             .send(message)
             .await
-            .expect("Failed to send message");
+            .map_err(|_| ClientError::Disconnected)
     }
 
-    pub async fn execute(&mut self, command: CommandRequestKind) {
+    pub async fn execute(&mut self, command: CommandRequestKind) -> Result<(), ClientError> {
         self.send(ClientWsMessage::Request {
             id: None,
             value: ClientWsRequest::Execute(command),
         })
-        .await;
+        .await
     }
 
-    /// Send a [`ClientWsRequest`] to the server and return the response from the server or [`None`] after 60 seconds.
-    pub async fn request(&mut self, request: ClientWsRequest) -> Option<ServerWsResponse> {
+    /// Send a [`ClientWsRequest`] to the server and return the response from the server or
+    /// [`ClientError::Timeout`] once the configured timeout has passed. The response is routed
+    /// directly to this call and never shows up in [`WsTransceiver::receive`].
+    pub async fn request(&self, request: ClientWsRequest) -> Result<ServerWsResponse, ClientError> {
         let id = Uuid::new_v4().to_string();
         let message = ClientWsMessage::Request {
             id: Some(id.clone()),
             value: request,
         };
 
-        self.send(message).await;
-        let end = Instant::now().checked_add(Duration::from_secs(60)).unwrap();
+        // Register before sending so a quick response cannot get lost.
+        let (response_tx, response_rx) = oneshot::channel();
+        let request = PendingRequest {
+            response_tx,
+            sent: false,
+        };
+        self.pending.lock().unwrap().insert(id.clone(), request);
 
-        while Instant::now() < end {
-            let message = self.receive().await;
-            let ServerWsMessage::Response {
-                id: response_id,
-                value,
-            } = message
-            else {
-                continue;
-            };
+        if let Err(e) = self.send(message).await {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(e);
+        }
 
-            if response_id != id {
-                continue;
+        match timeout(self.request_timeout, response_rx).await {
+            Ok(Ok(response)) => Ok(response),
+            // The connection was lost after the request had been sent.
+            Ok(Err(_)) => Err(ClientError::Disconnected),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                Err(ClientError::Timeout)
             }
+        }
+    }
+
+    /// Execute a command on the server and return its response.
+    pub async fn request_execute(
+        &self,
+        command: CommandRequestKind,
+    ) -> Result<Option<CommandResponseKind>, ClientError> {
+        let response = self.request(ClientWsRequest::Execute(command)).await?;
+
+        #[allow(irrefutable_let_patterns)]
+        let ServerWsResponse::Execute { failure, response } = response
+        else {
+            return Err(ClientError::UnexpectedResponse);
+        };
 
-            return Some(value);
+        if failure {
+            return Err(ClientError::ServerFailure);
         }
 
-        None
+        Ok(response)
     }
 
     /// Clear the incoming channel, should always be done to filter out old unnecessary logs.
-    pub async fn clear(&mut self) {
+    pub async fn clear(&mut self) -> Result<(), ClientError> {
         loop {
-            let res = self.to_client.try_recv();
-            if matches!(res, Err(TryRecvError::Empty)) {
-                return;
+            match self.to_client.try_recv() {
+                Ok(_) | Err(TryRecvError::Lagged(_)) => continue,
+                Err(TryRecvError::Empty) => return Ok(()),
+                Err(TryRecvError::Closed) => return Err(ClientError::Disconnected),
             }
         }
     }
 
-    /// Receive the next [`ServerWsMessage`].
-    pub async fn receive(&mut self) -> ServerWsMessage {
-        self.to_client
-            .recv()
-            .await
-            .expect("Failed to receive message")
+    /// Receive the next [`ServerWsMessage`]. Messages missed by falling behind are skipped and counted.
+    pub async fn receive(&mut self) -> Result<ServerWsMessage, ClientError> {
+        loop {
+            match self.to_client.recv().await {
+                Ok(message) => return Ok(message),
+                Err(RecvError::Lagged(count)) => self.record_lag(count),
+                Err(RecvError::Closed) => return Err(ClientError::Disconnected),
+            }
+        }
     }
+
+    /// Receive the next [`ServerWsMessage`] like [`WsTransceiver::receive`], but report missed
+    /// messages as [`ClientError::Lagged`] for callers which need to know about the gap.
+    pub async fn receive_checked(&mut self) -> Result<ServerWsMessage, ClientError> {
+        match self.to_client.recv().await {
+            Ok(message) => Ok(message),
+            Err(RecvError::Lagged(count)) => {
+                self.record_lag(count);
+                Err(ClientError::Lagged(count))
+            }
+            Err(RecvError::Closed) => Err(ClientError::Disconnected),
+        }
+    }
+
+    /// The amount of messages all transceivers sharing this connection have missed so far.
+    pub fn total_missed_messages(&self) -> u64 {
+        self.total_missed.load(Ordering::Relaxed)
+    }
+
+    fn record_lag(&mut self, count: u64) {
+        let total = self.total_missed.fetch_add(count, Ordering::Relaxed) + count;
+        warn!(
+            "Transceiver fell behind and missed {} messages, {} missed in total",
+            count, total
+        );
+    }
+}
+
+/// The outcome of an action as reported by the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionOutcome {
+    /// The server executed the action.
+    Executed,
+
+    /// The server refused to execute the action, usually because the player is no longer there.
+    Rejected,
+}
+
+impl ActionOutcome {
+    pub fn is_executed(&self) -> bool {
+        matches!(self, ActionOutcome::Executed)
+    }
+
+    /// Parse the response the game gives to an action, which is either SUCCESS or FAIL.
+    fn from_response(response: &str) -> Option<Self> {
+        match response.trim() {
+            "SUCCESS" => Some(ActionOutcome::Executed),
+            "FAIL" => Some(ActionOutcome::Rejected),
+            _ => None,
+        }
+    }
+}
+
+/// Quote a player name for a command. The game cannot escape quotes, so names containing one
+/// are refused rather than letting them end the argument early.
+fn quote_name(name: &str) -> Result<String, ClientError> {
+    if name.contains('"') {
+        return Err(ClientError::InvalidArgument(name.to_string()));
+    }
+
+    Ok(format!("\"{}\"", name))
+}
+
+/// Quote free text for a command, quotes in it are replaced as the game cannot escape them.
+fn quote_text(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "'"))
+}
+
+/// An entry of the server's admin list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Admin {
+    pub id: String,
+    pub role: String,
+    pub comment: String,
+}
+
+/// Parse the response to `GetAdminIds`, a count followed by tab separated `<id> <role> "<comment>"`
+/// entries.
+fn parse_admins(raw: &str) -> Vec<Admin> {
+    // The leading count has no role and is skipped like any other malformed entry.
+    raw.split('\t')
+        .filter_map(|entry| {
+            let mut parts = entry.trim().splitn(3, ' ');
+            let id = parts.next().filter(|id| !id.is_empty())?;
+            let role = parts.next()?;
+            let comment = parts.next().unwrap_or_default().trim_matches('"');
+
+            Some(Admin {
+                id: id.to_string(),
+                role: role.to_string(),
+                comment: comment.to_string(),
+            })
+        })
+        .collect()
 }
 
 #[async_trait::async_trait]
 pub trait WsTransceiverExt {
-    async fn message_player(&mut self, id: &PlayerId, message: &str);
-    async fn broadcast_message(&mut self, message: &str);
-    async fn punish_player(&mut self, player_name: &str, reason: &str);
-    async fn kick_player(&mut self, player_name: &str, reason: &str);
-    async fn get_playerinfo(&mut self, name: String) -> Option<PlayerInfo>;
-    async fn get_players(&mut self) -> Option<Vec<Player>>;
-    async fn execute_raw(&mut self, command: String);
+    async fn message_player(
+        &mut self,
+        id: &PlayerId,
+        message: &str,
+    ) -> Result<ActionOutcome, ClientError>;
+    async fn broadcast_message(&mut self, message: &str) -> Result<(), ClientError>;
+    async fn punish_player(
+        &mut self,
+        player_name: &str,
+        reason: &str,
+    ) -> Result<ActionOutcome, ClientError>;
+    async fn kick_player(
+        &mut self,
+        player_name: &str,
+        reason: &str,
+    ) -> Result<ActionOutcome, ClientError>;
+    async fn temp_ban_player(
+        &mut self,
+        player_name: &str,
+        duration: Duration,
+        reason: &str,
+        admin_name: &str,
+    ) -> Result<ActionOutcome, ClientError>;
+    async fn perma_ban_player(
+        &mut self,
+        player_name: &str,
+        reason: &str,
+        admin_name: &str,
+    ) -> Result<ActionOutcome, ClientError>;
+    async fn remove_from_squad(
+        &mut self,
+        player_name: &str,
+        reason: &str,
+    ) -> Result<ActionOutcome, ClientError>;
+    async fn switch_team_now(&mut self, player_name: &str) -> Result<ActionOutcome, ClientError>;
+    async fn switch_team_on_death(
+        &mut self,
+        player_name: &str,
+    ) -> Result<ActionOutcome, ClientError>;
+    async fn grant_admin_cam(
+        &mut self,
+        id: &PlayerId,
+        comment: &str,
+    ) -> Result<ActionOutcome, ClientError>;
+    async fn revoke_admin_cam(&mut self, id: &PlayerId) -> Result<ActionOutcome, ClientError>;
+    async fn get_admins(&mut self) -> Result<Vec<Admin>, ClientError>;
+    async fn get_playerinfo(&mut self, name: String) -> Result<Option<PlayerInfo>, ClientError>;
+    async fn get_players(&mut self) -> Result<Vec<Player>, ClientError>;
+    async fn get_gamestate(&mut self) -> Result<GameState, ClientError>;
+    async fn execute_raw(&mut self, command: String) -> Result<(), ClientError>;
+    async fn request_raw(&mut self, command: String) -> Result<ActionOutcome, ClientError>;
 }
 
 #[async_trait::async_trait]
 impl WsTransceiverExt for WsTransceiver {
-    async fn message_player(&mut self, id: &PlayerId, message: &str) {
+    async fn message_player(
+        &mut self,
+        id: &PlayerId,
+        message: &str,
+    ) -> Result<ActionOutcome, ClientError> {
         let command = format!("Message {} {}", id.to_string(), message);
-        self.execute_raw(command).await;
+        self.request_raw(command).await
     }
 
-    async fn broadcast_message(&mut self, message: &str) {
-        let players = self
-            .request(ClientWsRequest::Execute(CommandRequestKind::GetPlayerIds))
-            .await
-            .unwrap();
-
-        let ServerWsResponse::Execute {
-            failure: _,
-            response: Some(CommandResponseKind::GetPlayerIds(players)),
-        } = players
-        else {
-            return;
-        };
+    async fn broadcast_message(&mut self, message: &str) -> Result<(), ClientError> {
+        let players = self.get_players().await?;
 
         for player in players {
             let command = format!("Message {} {}", player.id.to_string(), message);
-            self.execute_raw(command).await;
+            self.execute_raw(command).await?;
         }
+
+        Ok(())
+    }
+
+    async fn punish_player(
+        &mut self,
+        player_name: &str,
+        reason: &str,
+    ) -> Result<ActionOutcome, ClientError> {
+        let command = format!("Punish {} {}", quote_name(player_name)?, quote_text(reason));
+        self.request_raw(command).await
+    }
+
+    async fn kick_player(
+        &mut self,
+        player_name: &str,
+        reason: &str,
+    ) -> Result<ActionOutcome, ClientError> {
+        let command = format!("Kick {} {}", quote_name(player_name)?, quote_text(reason));
+        self.request_raw(command).await
     }
 
-    async fn punish_player(&mut self, player_name: &str, message: &str) {
-        let command = format!("Punish {} {}", player_name, message);
-        self.execute_raw(command).await;
+    async fn temp_ban_player(
+        &mut self,
+        player_name: &str,
+        duration: Duration,
+        reason: &str,
+        admin_name: &str,
+    ) -> Result<ActionOutcome, ClientError> {
+        // The game only accepts whole hours, round up so a ban is never shorter than asked for.
+        let hours = duration.as_secs().div_ceil(60 * 60).max(1);
+        let command = format!(
+            "TempBan {} {} {} {}",
+            quote_name(player_name)?,
+            hours,
+            quote_text(reason),
+            quote_text(admin_name)
+        );
+        self.request_raw(command).await
     }
 
-    async fn kick_player(&mut self, player_name: &str, reason: &str) {
-        let command = format!("Punish {} {}", player_name, reason);
-        self.execute_raw(command).await;
+    async fn perma_ban_player(
+        &mut self,
+        player_name: &str,
+        reason: &str,
+        admin_name: &str,
+    ) -> Result<ActionOutcome, ClientError> {
+        let command = format!(
+            "PermaBan {} {} {}",
+            quote_name(player_name)?,
+            quote_text(reason),
+            quote_text(admin_name)
+        );
+        self.request_raw(command).await
     }
 
-    async fn get_playerinfo(&mut self, name: String) -> Option<PlayerInfo> {
+    async fn remove_from_squad(
+        &mut self,
+        player_name: &str,
+        reason: &str,
+    ) -> Result<ActionOutcome, ClientError> {
+        let command = format!(
+            "RemovePlayerFromPlatoon {} {}",
+            quote_name(player_name)?,
+            quote_text(reason)
+        );
+        self.request_raw(command).await
+    }
+
+    async fn switch_team_now(&mut self, player_name: &str) -> Result<ActionOutcome, ClientError> {
+        let command = format!("SwitchTeamNow {}", quote_name(player_name)?);
+        self.request_raw(command).await
+    }
+
+    async fn switch_team_on_death(
+        &mut self,
+        player_name: &str,
+    ) -> Result<ActionOutcome, ClientError> {
+        let command = format!("SwitchTeamOnDeath {}", quote_name(player_name)?);
+        self.request_raw(command).await
+    }
+
+    /// Admin cam access is granted through the spectator admin role.
+    async fn grant_admin_cam(
+        &mut self,
+        id: &PlayerId,
+        comment: &str,
+    ) -> Result<ActionOutcome, ClientError> {
+        let command = format!("AdminAdd {} spectator {}", id, quote_text(comment));
+        self.request_raw(command).await
+    }
+
+    async fn revoke_admin_cam(&mut self, id: &PlayerId) -> Result<ActionOutcome, ClientError> {
+        let command = format!("AdminDel {}", id);
+        self.request_raw(command).await
+    }
+
+    async fn get_admins(&mut self) -> Result<Vec<Admin>, ClientError> {
         let response = self
-            .request(ClientWsRequest::Execute(CommandRequestKind::GetPlayerInfo(
-                name
-            )))
+            .request_execute(CommandRequestKind::Raw {
+                command: "GetAdminIds".to_string(),
+                long_response: true,
+            })
             .await?;
 
-        let ServerWsResponse::Execute {
-            failure: _,
-            response: Some(CommandResponseKind::GetPlayerInfo(info)),
-        } = response
-        else {
-            return None;
+        let Some(CommandResponseKind::Raw(raw)) = response else {
+            return Err(ClientError::UnexpectedResponse);
         };
 
-        info
+        Ok(parse_admins(&raw))
     }
 
-    async fn get_players(&mut self) -> Option<Vec<Player>> {
+    async fn get_playerinfo(&mut self, name: String) -> Result<Option<PlayerInfo>, ClientError> {
         let response = self
-            .request(ClientWsRequest::Execute(CommandRequestKind::GetPlayerIds))
+            .request_execute(CommandRequestKind::GetPlayerInfo(name))
             .await?;
 
-        let ServerWsResponse::Execute {
-            failure: _,
-            response: Some(CommandResponseKind::GetPlayerIds(players)),
-        } = response
-        else {
-            return None;
+        let Some(CommandResponseKind::GetPlayerInfo(info)) = response else {
+            return Err(ClientError::UnexpectedResponse);
         };
 
-        Some(players)
+        Ok(info)
     }
 
-    async fn execute_raw(&mut self, command: String) {
-        self.send(ClientWsMessage::Request {
-            id: None,
-            value: ClientWsRequest::Execute(CommandRequestKind::Raw {
-                command,
+    async fn get_players(&mut self) -> Result<Vec<Player>, ClientError> {
+        let response = self
+            .request_execute(CommandRequestKind::GetPlayerIds)
+            .await?;
+
+        let Some(CommandResponseKind::GetPlayerIds(players)) = response else {
+            return Err(ClientError::UnexpectedResponse);
+        };
+
+        Ok(players)
+    }
+
+    async fn get_gamestate(&mut self) -> Result<GameState, ClientError> {
+        let response = self
+            .request_execute(CommandRequestKind::Raw {
+                command: "GetGameState".to_string(),
                 long_response: false,
-            }),
+            })
+            .await?;
+
+        let Some(CommandResponseKind::Raw(raw)) = response else {
+            return Err(ClientError::UnexpectedResponse);
+        };
+
+        raw.parse().map_err(|e| {
+            warn!("Failed to parse game state \"{}\": {}", raw, e);
+            ClientError::UnexpectedResponse
+        })
+    }
+
+    async fn execute_raw(&mut self, command: String) -> Result<(), ClientError> {
+        self.execute(CommandRequestKind::Raw {
+            command,
+            long_response: false,
         })
-        .await;
+        .await
+    }
+
+    /// Execute a raw command and wait for the game to report whether it succeeded.
+    async fn request_raw(&mut self, command: String) -> Result<ActionOutcome, ClientError> {
+        let response = self
+            .request_execute(CommandRequestKind::Raw {
+                command: command.clone(),
+                long_response: false,
+            })
+            .await?;
+
+        let Some(CommandResponseKind::Raw(raw)) = response else {
+            return Err(ClientError::UnexpectedResponse);
+        };
+
+        ActionOutcome::from_response(&raw).ok_or_else(|| {
+            warn!("Unexpected response \"{}\" to {}", raw, command);
+            ClientError::UnexpectedResponse
+        })
+    }
+}
+
+/// Send the token to the server and wait for it to confirm the authentication.
+async fn login(config: &ClientConfig) -> Result<RawWsClient, ClientError> {
+    let (mut ws_client, _) = connect_async(&config.address).await?;
+    ws_client
+        .send(Message::Text(config.token.to_string()))
+        .await?;
+
+    let Some(Ok(message)) = ws_client.next().await else {
+        return Err(ClientError::AuthRejected);
+    };
+
+    let server_message = serde_json::from_str::<ServerWsMessage>(message.into_text()?.as_str())?;
+
+    if !matches!(server_message, ServerWsMessage::Authenticated) {
+        return Err(ClientError::AuthRejected);
+    }
+
+    Ok(ws_client)
+}
+
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Drive the connection to the server and reconnect with an exponential backoff whenever it drops.
+/// The channels outlive each individual connection so all transceivers are rewired transparently.
+async fn connection_loop(
+    config: ClientConfig,
+    mut raw: RawWsClient,
+    mut to_server: mpsc::Receiver<ClientWsMessage>,
+    to_client: broadcast::Sender<ServerWsMessage>,
+    pending: PendingRequests,
+) {
+    loop {
+        let (sink, stream) = raw.split();
+
+        let result = tokio::select! {
+            res = to_server_loop(sink, &mut to_server, &pending) => res,
+            res = to_client_loop(stream, &to_client, &pending) => res,
+        };
+
+        // Responses to requests sent over this connection will never arrive.
+        pending.lock().unwrap().retain(|_, request| !request.sent);
+
+        match result {
+            Ok(true) => {
+                debug!("All transceivers dropped -> Closing connection");
+                return;
+            }
+            Ok(false) => warn!("Connection to wise closed"),
+            Err(e) => warn!("Connection to wise failed: {}", e),
+        }
+
+        let mut delay = MIN_RECONNECT_DELAY;
+        raw = loop {
+            info!(
+                "Reconnecting to wise in {}",
+                humantime::format_duration(delay)
+            );
+            sleep(delay).await;
+
+            match login(&config).await {
+                Ok(raw) => break raw,
+                Err(e) => warn!("Failed to reconnect to wise: {}", e),
+            }
+
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        };
+
+        info!("Reconnected to wise");
+        _ = to_client.send(ServerWsMessage::Authenticated);
     }
 }
 
 /// Continously receive client messages from the receiver and send them to the server.
+/// Returns `true` if there are no more senders left.
 async fn to_server_loop(
-    mut sink: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
-    mut to_server: mpsc::Receiver<ClientWsMessage>,
-) -> Result<(), Box<dyn Error>> {
+    mut sink: SplitSink<RawWsClient, Message>,
+    to_server: &mut mpsc::Receiver<ClientWsMessage>,
+    pending: &PendingRequests,
+) -> Result<bool, ClientError> {
     while let Some(message) = to_server.recv().await {
         trace!("Sending {:?} to server", message);
 
+        // Marked before sending, the message is gone from the queue even if sending fails.
+        if let ClientWsMessage::Request { id: Some(id), .. } = &message {
+            if let Some(request) = pending.lock().unwrap().get_mut(id) {
+                request.sent = true;
+            }
+        }
+
         let json = match serde_json::to_string(&message) {
             Ok(json) => json,
             Err(err) => {
@@ -272,17 +754,20 @@ async fn to_server_loop(
         sink.send(Message::text(json)).await?;
     }
 
-    Ok(())
+    Ok(true)
 }
 
 /// Continously receive server messages from the stream and send them to the client.
+/// Returns once the stream has ended.
 async fn to_client_loop(
-    mut stream: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
-    to_client: broadcast::Sender<ServerWsMessage>,
-) -> Result<(), Box<dyn Error>> {
-    while let Some(Ok(ws_message)) = stream.next().await {
-        let text = match ws_message {
+    mut stream: SplitStream<RawWsClient>,
+    to_client: &broadcast::Sender<ServerWsMessage>,
+    pending: &PendingRequests,
+) -> Result<bool, ClientError> {
+    while let Some(ws_message) = stream.next().await {
+        let text = match ws_message? {
             Message::Text(json) => json,
+            Message::Close(_) => break,
             _ => {
                 warn!("Server sent non text message");
                 continue;
@@ -297,8 +782,77 @@ async fn to_client_loop(
             }
         };
 
-        to_client.send(message)?;
+        // Route responses to the request waiting for them, everything else is broadcasted.
+        if let ServerWsMessage::Response { id, value } = message {
+            match pending.lock().unwrap().remove(&id) {
+                Some(request) => _ = request.response_tx.send(value),
+                None => trace!("Received response for unknown request {}", id),
+            }
+            continue;
+        }
+
+        // Sending only fails if there are currently no receivers which is not an error.
+        _ = to_client.send(message);
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_admin_list() {
+        let raw = "2\t76561198000000001 owner \"Some Owner\"\t76561198000000002 spectator \"Hellvents Sky Eye\"";
+
+        let admins = parse_admins(raw);
+
+        assert_eq!(
+            admins,
+            vec![
+                Admin {
+                    id: "76561198000000001".to_string(),
+                    role: "owner".to_string(),
+                    comment: "Some Owner".to_string(),
+                },
+                Admin {
+                    id: "76561198000000002".to_string(),
+                    role: "spectator".to_string(),
+                    comment: "Hellvents Sky Eye".to_string(),
+                },
+            ]
+        );
     }
 
-    Ok(())
+    #[test]
+    fn parses_empty_admin_list() {
+        assert!(parse_admins("0").is_empty());
+    }
+
+    #[test]
+    fn parses_action_outcome() {
+        assert_eq!(
+            ActionOutcome::from_response("SUCCESS"),
+            Some(ActionOutcome::Executed)
+        );
+        assert_eq!(
+            ActionOutcome::from_response("FAIL\n"),
+            Some(ActionOutcome::Rejected)
+        );
+        assert_eq!(ActionOutcome::from_response("Player not found"), None);
+    }
+
+    #[test]
+    fn quotes_cannot_break_out_of_arguments() {
+        assert_eq!(quote_name("Some Player").unwrap(), "\"Some Player\"");
+        assert!(matches!(
+            quote_name("Evil\" 24 \"Player"),
+            Err(ClientError::InvalidArgument(_))
+        ));
+        assert_eq!(
+            quote_text("Your kill with \"M1 GARAND\""),
+            "\"Your kill with 'M1 GARAND'\""
+        );
+    }
 }
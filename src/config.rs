@@ -1,10 +1,25 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use clap::Parser;
 use config::{Config, ConfigError, File};
 use serde::Deserialize;
 
-use crate::client::ClientConfig;
+use crate::{
+    checkpoint::CheckpointConfig,
+    client::ClientConfig,
+    events::MatchEndConfig,
+    history::HistoryConfig,
+    manage::{
+        control::ControlConfig,
+        permissions::Permission,
+        scheduler::{ScheduleConfig, SchedulerConfig},
+    },
+    penalties::PenaltyConfig,
+    weapons::WeaponConfig,
+};
 
 #[derive(Debug, Clone, Parser)]
 #[command(name = "Hellvents")]
@@ -18,18 +33,58 @@ pub struct FileConfig {
     pub wise: ClientConfig,
 
     pub admin: AdminConfig,
+
+    #[serde(default)]
+    pub weapons: WeaponConfig,
+
+    #[serde(default)]
+    pub penalties: PenaltyConfig,
+
+    #[serde(default)]
+    pub history: HistoryConfig,
+
+    #[serde(default)]
+    pub match_end: MatchEndConfig,
+
+    #[serde(default)]
+    pub checkpoint: CheckpointConfig,
+
+    #[serde(default)]
+    pub scheduler: SchedulerConfig,
+
+    #[serde(default)]
+    pub control: ControlConfig,
+
+    #[serde(default)]
+    pub schedules: Vec<ScheduleConfig>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct AdminConfig {
+    /// Players with the owner role.
+    #[serde(default)]
     pub allowed_ids: Vec<String>,
+
+    /// The permissions of every role by its name.
+    #[serde(default)]
+    pub roles: HashMap<String, Vec<Permission>>,
+
+    /// The role of every admin by their player id.
+    #[serde(default)]
+    pub members: HashMap<String, String>,
 }
 
-pub fn parse_config() -> Result<FileConfig, ConfigError> {
+/// Load the config file given on the command line. Its path is returned too, so the config
+/// can be reloaded later.
+pub fn parse_config() -> Result<(PathBuf, FileConfig), ConfigError> {
     let cli = CliConfig::parse();
+    let config = load_config(&cli.config_file)?;
+    Ok((cli.config_file, config))
+}
 
+pub fn load_config(path: &Path) -> Result<FileConfig, ConfigError> {
     let config = Config::builder()
-        .add_source(File::with_name(cli.config_file.to_str().unwrap()))
+        .add_source(File::with_name(path.to_str().unwrap()))
         .build()?;
 
     config.try_deserialize()
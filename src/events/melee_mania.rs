@@ -1,14 +1,15 @@
 use std::{
     collections::HashMap,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
-use log::{debug, info};
-use tokio::{
-    sync::Mutex,
-    time::{sleep, sleep_until},
-};
+use derive_new::new;
+use log::{debug, error, info, warn};
+use tokio::{sync::Mutex, time::sleep_until};
 use tokio_util::sync::CancellationToken;
 use wise_api::{
     events::RconEvent,
@@ -20,15 +21,25 @@ use wise_api::{
 };
 
 use crate::{
-    client::{WsTransceiver, WsTransceiverExt},
+    checkpoint::{from_past_wall_clock, from_wall_clock, to_wall_clock, EventState},
+    client::{ClientError, WsTransceiver, WsTransceiverExt},
+    history::EventRecorder,
     manage::command::StartEvent,
     messages::melee_mania::*,
+    penalties::{KillKind, KillPolicy, PenaltyAction, PenaltyLadder},
+    weapons::{WeaponCatalogue, WeaponCategory, WeaponRule},
 };
 
-use super::Event;
+use super::{Claim, Event, EventContext, EventPhase, EventStatus, Forgiveness, PauseState};
+
+/// Every event restricting weapons penalizes kills, two of them would punish twice.
+pub const CLAIMS: &[Claim] = &[Claim::Kills];
 
+/// Configures Melee Mania and every other event which restricts the weapons players may use.
 #[derive(Debug, Clone)]
 pub struct MeleeManiaConfig {
+    name: &'static str,
+    rule: WeaponRule,
     duration: Duration,
     delay: Duration,
 }
@@ -38,6 +49,8 @@ const TWO_MINUTES: u64 = 60 * 2 + 10;
 impl Default for MeleeManiaConfig {
     fn default() -> Self {
         Self {
+            name: "Melee Mania",
+            rule: WeaponRule::Allow(vec![WeaponCategory::Melee]),
             duration: Duration::from_secs(FIVE_MINUTES),
             delay: Duration::from_secs(TWO_MINUTES),
         }
@@ -46,87 +59,145 @@ impl Default for MeleeManiaConfig {
 
 impl MeleeManiaConfig {
     pub fn from_config(config: &StartEvent) -> Self {
-        #[allow(irrefutable_let_patterns)]
-        let StartEvent::MeleeMania { duration, delay }: &StartEvent = config
-        else {
-            panic!("Tried to build config from invalid start event");
-        };
-
-        Self {
-            duration: duration.clone(),
-            delay: delay.clone(),
+        match config {
+            StartEvent::MeleeMania { duration, delay } => Self {
+                duration: *duration,
+                delay: *delay,
+                ..Default::default()
+            },
+            StartEvent::RestrictedArsenal {
+                delay,
+                duration,
+                allow,
+                deny,
+            } => {
+                // Clap guarantees exactly one of the lists is given.
+                let rule = match allow.is_empty() {
+                    true => WeaponRule::Deny(deny.clone()),
+                    false => WeaponRule::Allow(allow.clone()),
+                };
+
+                Self {
+                    name: "Restricted Arsenal",
+                    rule,
+                    duration: *duration,
+                    delay: *delay,
+                }
+            }
+            _ => panic!("Tried to build config from invalid start event"),
         }
     }
 }
 
 #[derive(Clone)]
 pub struct MeleeMania {
-    infractions: Arc<Mutex<HashMap<PlayerId, i32>>>,
+    infractions: Arc<Mutex<HashMap<PlayerId, Infractions>>>,
+    /// When the event ends if it is never paused.
     end: Instant,
+
+    /// Set while the event is paused between matches. The end moves back by the time spent
+    /// paused.
+    pause: PauseState,
     config: Arc<MeleeManiaConfig>,
+
+    /// Whether the start has been announced to the players.
+    started: Arc<AtomicBool>,
+
+    /// Log messages missed while enforcing, kills among them went unpunished.
+    missed: Arc<AtomicU64>,
+
+    /// Whether the event was picked up again after a restart and has already been announced.
+    resumed: bool,
     token: CancellationToken,
     transceiver: WsTransceiver,
+    weapons: Arc<WeaponCatalogue>,
+    penalties: Arc<PenaltyLadder>,
+    history: EventRecorder,
+}
+
+/// The infractions of a single player.
+#[derive(Debug, Clone)]
+struct Infractions {
+    player: Player,
+    count: u32,
+
+    /// When the player last committed an infraction or had one forgiven.
+    last: Instant,
 }
 
+#[derive(new)]
 struct PenaltyContext {
     killer: Player,
     victim: Player,
     weapon: String,
-}
+    rule: WeaponRule,
+    kind: KillKind,
 
-impl PenaltyContext {
-    fn new(killer: Player, victim: Player, weapon: String) -> Self {
-        Self {
-            killer,
-            victim,
-            weapon,
-        }
-    }
+    /// Whether the weapon broke the rule, kills may also be penalized for what they are.
+    breaks_rule: bool,
+
+    /// How many infractions the killer has committed including this one.
+    infraction: u32,
 }
 
+#[async_trait::async_trait]
 impl Event for MeleeMania {
-    fn start(&self) {
-        let clone = self.clone();
-        _ = tokio::spawn(clone.run());
+    fn name(&self) -> &'static str {
+        self.config.name
     }
 
-    fn stop(&self) {
-        self.token.cancel();
-    }
-}
-
-impl MeleeMania {
-    pub fn new(config: MeleeManiaConfig, transceiver: WsTransceiver) -> Self {
-        Self {
-            infractions: Default::default(),
-            end: Instant::now()
-                .checked_add(config.duration + config.delay)
-                .unwrap(),
-            config: Arc::new(config),
-            token: Default::default(),
-            transceiver,
+    async fn prepare(&mut self) -> Result<(), ClientError> {
+        if self.started.load(Ordering::Relaxed) {
+            info!("Resuming {} with config {:?}", self.name(), self.config);
+            return Ok(());
         }
-    }
 
-    async fn run(mut self) {
-        info!("Starting Melee Mania with config {:?}", self.config);
+        info!("Starting {} with config {:?}", self.name(), self.config);
 
-        let announce_info = info_message(&self.config.delay, &self.config.duration);
-        let announce_start = start_message(&self.config.duration);
-        let announce_end = end_message();
+        let announce_info = info_message(
+            self.name(),
+            &self.config.rule,
+            &self.penalties,
+            &self.config.delay,
+            &self.config.duration,
+        );
+        let announce_start = start_message(
+            self.name(),
+            &self.config.rule,
+            &self.penalties,
+            &self.config.duration,
+        );
 
-        debug!("Broadcasting info message");
-        self.transceiver.broadcast_message(&announce_info).await;
-        tokio::select! {
-            _ = sleep(self.config.delay) => {},
-            _ = self.token.cancelled() => return,
-        };
+        if !self.resumed {
+            debug!("Broadcasting info message");
+            self.transceiver.broadcast_message(&announce_info).await?;
+        }
+
+        loop {
+            let start = self.end() - self.config.duration;
+            tokio::select! {
+                _ = sleep_until(start.into()), if !self.pause.is_paused() => {
+                    // The start moves back if the event was paused in the meantime.
+                    if Instant::now() >= self.end() - self.config.duration {
+                        break;
+                    }
+                }
+                _ = self.pause.changed() => {}
+                _ = self.token.cancelled() => return Ok(()),
+            };
+        }
         debug!("Broadcasting start message");
-        self.transceiver.broadcast_message(&announce_start).await;
+        self.transceiver.broadcast_message(&announce_start).await?;
 
-        self.transceiver.clear().await;
+        self.transceiver.clear().await?;
+        self.started.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn run(&mut self) {
         info!(
-            "Enforcing Melee Mania for {}",
+            "Enforcing {} for {}",
+            self.name(),
             humantime::format_duration(self.config.duration)
         );
 
@@ -136,11 +207,26 @@ impl MeleeMania {
                     info!("Cancellation detected -> Stopping");
                     break;
                 }
-                _ = sleep_until(self.end.into()) => {
-                    info!("Reached end of Melee Mania -> Stopping");
-                    break;
+                _ = sleep_until(self.end().into()), if !self.pause.is_paused() => {
+                    if Instant::now() >= self.end() {
+                        info!("Reached end of {} -> Stopping", self.name());
+                        break;
+                    }
                 }
-                message = self.transceiver.receive() => {
+                _ = self.pause.changed() => {}
+                message = self.transceiver.receive_checked() => {
+                    let message = match message {
+                        Ok(message) => message,
+                        Err(ClientError::Lagged(count)) => {
+                            self.missed.fetch_add(count, Ordering::Relaxed);
+                            continue;
+                        }
+                        Err(e) => {
+                            error!("Failed to receive message -> Stopping: {}", e);
+                            break;
+                        }
+                    };
+
                     let ServerWsMessage::Rcon(rcon_event) = message else {
                         continue;
                     };
@@ -155,12 +241,187 @@ impl MeleeMania {
             );
         }
 
-        self.token.cancel();
+        let missed = self.missed.load(Ordering::Relaxed);
+        if missed > 0 {
+            warn!(
+                "Missed {} messages while enforcing, some kills may have gone unpunished",
+                missed
+            );
+        }
+    }
+
+    async fn finished(&mut self) {
+        // Players were never told the event started, so there is nothing to end.
+        if !self.started.load(Ordering::Relaxed) {
+            return;
+        }
+
         debug!("Broadcasting end message");
-        self.transceiver.broadcast_message(&announce_end).await;
+        if let Err(e) = self
+            .transceiver
+            .broadcast_message(&end_message(self.name()))
+            .await
+        {
+            warn!("Failed to broadcast end message: {}", e);
+        }
+    }
+
+    async fn status(&self) -> EventStatus {
+        let now = Instant::now();
+        let end = self.end();
+        let start = end - self.config.duration;
+
+        let (phase, remaining) = if self.token.is_cancelled() || now >= end {
+            (EventPhase::Ended, None)
+        } else if self.pause.is_paused() {
+            (EventPhase::Paused, Some(end - now.max(start)))
+        } else if now < start {
+            (EventPhase::Announced, Some(start - now))
+        } else {
+            (EventPhase::Running, Some(end - now))
+        };
+
+        let guard = self.infractions.lock().await;
+        let counts: Vec<u32> = guard
+            .values()
+            .map(|record| self.penalties.decay(record.count, record.last, now).0)
+            .filter(|count| *count > 0)
+            .collect();
+        let counters = vec![
+            (
+                "Infractions",
+                counts.iter().map(|count| *count as u64).sum(),
+            ),
+            ("Penalized players", counts.len() as u64),
+            ("Missed log messages", self.missed.load(Ordering::Relaxed)),
+        ];
+
+        EventStatus::new(self.name(), phase, remaining, counters)
+    }
+
+    async fn state(&self) -> EventState {
+        let infractions = self
+            .infractions
+            .lock()
+            .await
+            .values()
+            .map(|record| {
+                let last = to_wall_clock(record.last);
+                (record.player.clone(), record.count, last)
+            })
+            .collect();
+
+        EventState::MeleeMania {
+            started: self.started.load(Ordering::Relaxed),
+            end: to_wall_clock(self.end()),
+            paused: self.pause.is_paused(),
+            infractions,
+        }
+    }
+
+    async fn restore(&mut self, state: EventState) {
+        let EventState::MeleeMania {
+            started,
+            end,
+            paused,
+            infractions,
+        } = state
+        else {
+            warn!("Cannot restore {} from {:?}", self.name(), state);
+            return;
+        };
+
+        let mut guard = self.infractions.lock().await;
+        for (player, count, last) in infractions {
+            // Clean play from before the restart still counts towards forgiveness.
+            let record = Infractions {
+                player: player.clone(),
+                count,
+                last: from_past_wall_clock(last),
+            };
+            guard.insert(player.id, record);
+        }
+
+        self.end = from_wall_clock(end);
+        if paused {
+            self.pause.pause(self.name());
+        }
+        self.started.store(started, Ordering::Relaxed);
+        self.resumed = true;
+    }
+
+    async fn pause(&self) {
+        self.pause.pause(self.name());
+    }
+
+    async fn resume(&self) {
+        if !self.pause.resume(self.name()) || !self.started.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let message = running_message(
+            self.name(),
+            &self.config.rule,
+            &self.penalties,
+            &self.end().saturating_duration_since(Instant::now()),
+        );
+        if let Err(e) = self.transceiver.clone().broadcast_message(&message).await {
+            warn!("Failed to announce resumed {}: {}", self.name(), e);
+        }
+    }
+
+    async fn forgive(&self, player: &str) -> Forgiveness {
+        let mut guard = self.infractions.lock().await;
+        let id = guard
+            .iter()
+            .find(|(id, record)| {
+                id.to_string() == player || record.player.name.eq_ignore_ascii_case(player)
+            })
+            .map(|(id, _)| id.clone());
+
+        match id.and_then(|id| guard.remove(&id)) {
+            Some(record) => {
+                info!(
+                    "Forgave {} infractions of {:?}",
+                    record.count, record.player
+                );
+                Forgiveness::Forgiven(record.player)
+            }
+            None => Forgiveness::UnknownPlayer,
+        }
+    }
+}
+
+impl MeleeMania {
+    pub fn new(config: MeleeManiaConfig, ctx: EventContext) -> Self {
+        Self {
+            infractions: Default::default(),
+            end: Instant::now()
+                .checked_add(config.duration + config.delay)
+                .unwrap(),
+            pause: PauseState::default(),
+            config: Arc::new(config),
+            started: Arc::default(),
+            missed: Arc::default(),
+            resumed: false,
+            token: ctx.token,
+            transceiver: ctx.transceiver,
+            weapons: ctx.weapons,
+            penalties: ctx.penalties,
+            history: ctx.history,
+        }
+    }
+
+    fn end(&self) -> Instant {
+        self.end + self.pause.paused_for()
     }
 
     async fn handle_rcon_event(&mut self, rcon_event: RconEvent) {
+        // Nothing is enforced between matches.
+        if self.pause.is_paused() {
+            return;
+        }
+
         match rcon_event {
             RconEvent::Log(LogLine { timestamp: _, kind }) => self.handle_log(&kind).await,
             _ => return,
@@ -173,8 +434,18 @@ impl MeleeMania {
             connect: true,
         } = log
         {
-            let message = running_message(&self.end.duration_since(Instant::now()));
-            self.transceiver.message_player(&player.id, &message).await;
+            let message = running_message(
+                self.name(),
+                &self.config.rule,
+                &self.penalties,
+                &self.end().saturating_duration_since(Instant::now()),
+            );
+            if let Err(e) = self.transceiver.message_player(&player.id, &message).await {
+                warn!(
+                    "Failed to inform {:?} about running event: {}",
+                    player.id, e
+                );
+            }
             return;
         }
 
@@ -183,91 +454,218 @@ impl MeleeMania {
             killer_faction: _,
             victim,
             victim_faction: _,
-            is_teamkill: _,
+            is_teamkill,
             weapon,
         } = log
         else {
             return;
         };
 
-        if is_weapon_melee(&weapon) {
-            debug!("Not punishing {:?} for the use of {}", &killer.id, weapon);
+        let kind = self.classify_kill(killer, victim, *is_teamkill, weapon);
+        let breaks_rule = !self.config.rule.permits(&self.weapons, weapon);
+        let penalize = match self.penalties.kills.policy(kind) {
+            KillPolicy::Ignore => false,
+            KillPolicy::Enforce => breaks_rule,
+            KillPolicy::Penalize => true,
+        };
+
+        if !penalize {
+            debug!(
+                "Not punishing {:?} for the {:?} kill with {}",
+                &killer.id, kind, weapon
+            );
             return;
         }
 
-        let ctx = PenaltyContext::new(killer.clone(), victim.clone(), weapon.clone());
-        self.calculate_penalty(&killer.id)
+        let infraction = self.record_infraction(killer).await;
+        let penalty = self.penalties.penalty(infraction).clone();
+        let ctx = PenaltyContext::new(
+            killer.clone(),
+            victim.clone(),
+            weapon.clone(),
+            self.config.rule.clone(),
+            kind,
+            breaks_rule,
+            infraction,
+        );
+        if let Err(e) = ctx
+            .enforce(&penalty, &mut self.transceiver, &self.history)
             .await
-            .execute(&ctx, &mut self.transceiver)
-            .await;
+        {
+            warn!(
+                "Failed to enforce penalty {:?} for {:?}: {}",
+                penalty, &ctx.killer, e
+            );
+        }
+    }
+
+    fn classify_kill(
+        &self,
+        killer: &Player,
+        victim: &Player,
+        is_teamkill: bool,
+        weapon: &str,
+    ) -> KillKind {
+        let categories = self.weapons.categories(weapon);
+        kill_kind(&categories, killer.id == victim.id, is_teamkill)
     }
 
-    async fn calculate_penalty(&mut self, id: &PlayerId) -> PenaltyKind {
+    /// Count an infraction of the player and return how many they have committed after
+    /// forgiving those earned back through clean play.
+    async fn record_infraction(&mut self, player: &Player) -> u32 {
+        let now = Instant::now();
         let mut guard = self.infractions.lock().await;
-        if !guard.contains_key(id) {
-            guard.insert(id.clone(), 0);
+        let record = guard.entry(player.id.clone()).or_insert(Infractions {
+            player: player.clone(),
+            count: 0,
+            last: now,
+        });
+
+        let (count, _) = self.penalties.decay(record.count, record.last, now);
+        if count < record.count {
+            debug!(
+                "Forgave {} infractions of {:?} for clean play",
+                record.count - count,
+                player
+            );
         }
 
-        let count = guard.get_mut(id).unwrap();
-        *count += 1;
-
-        match count {
-            1 | 2 => PenaltyKind::Punish,
-            _ => PenaltyKind::Kick,
-        }
+        record.count = count + 1;
+        record.last = now;
+        record.count
     }
 }
 
-#[derive(Debug)]
-enum PenaltyKind {
-    Punish,
-    Kick,
-}
-
-impl PenaltyKind {
-    pub async fn execute(&self, ctx: &PenaltyContext, transceiver: &mut WsTransceiver) {
-        let killer_text = match self {
-            PenaltyKind::Punish => format!(
-                "\"Your kill with {} violated the melee only rule. You may only use your melee weapon during this event.\"",
-                ctx.weapon
-            ),
-            PenaltyKind::Kick => format!(
-                "\"Your kill with {} violated the melee only rule. Due to previous infractions you have been kicked.\"",
-                ctx.weapon
-            ),
-        };
-
-        let victim_text = match self {
-            PenaltyKind::Punish => format!(
-                "Your killer {} has been redeployed for killing you with {}.",
-                ctx.killer.name, ctx.weapon
-            ),
-            PenaltyKind::Kick => format!(
-                "Your killer {} has been kicked for killing you with {}.",
-                ctx.killer.name, ctx.weapon
-            ),
-        };
+impl PenaltyContext {
+    async fn enforce(
+        &self,
+        penalty: &PenaltyAction,
+        transceiver: &mut WsTransceiver,
+        history: &EventRecorder,
+    ) -> Result<(), ClientError> {
+        let killer_text = penalty_message(
+            penalty,
+            self.infraction,
+            self.kind,
+            &self.weapon,
+            &self.rule,
+            self.breaks_rule,
+        );
 
         debug!(
             "Enforcing penalty {:?} for {:?} for the use of {}",
-            self, &ctx.killer, &ctx.weapon
+            penalty, &self.killer, &self.weapon
         );
 
-        match self {
-            PenaltyKind::Punish => transceiver.punish_player(&ctx.killer.name, &killer_text),
-            PenaltyKind::Kick => transceiver.kick_player(&ctx.killer.name, &killer_text),
+        let outcome = match penalty {
+            PenaltyAction::Warn => {
+                transceiver
+                    .message_player(&self.killer.id, &killer_text)
+                    .await?
+            }
+            PenaltyAction::Punish => {
+                transceiver
+                    .punish_player(&self.killer.name, &killer_text)
+                    .await?
+            }
+            PenaltyAction::Kick => {
+                transceiver
+                    .kick_player(&self.killer.name, &killer_text)
+                    .await?
+            }
+            PenaltyAction::TempBan { .. } => {
+                let duration = penalty.ban_duration().unwrap_or_default();
+                transceiver
+                    .temp_ban_player(&self.killer.name, duration, &killer_text, "Hellvents")
+                    .await?
+            }
+        };
+
+        if !outcome.is_executed() {
+            warn!(
+                "Server rejected penalty {:?} for {:?}, not informing victim",
+                penalty, &self.killer
+            );
+            return Ok(());
         }
-        .await;
 
-        transceiver
-            .message_player(&ctx.victim.id, &victim_text)
+        history
+            .penalty(
+                &self.killer,
+                &self.victim,
+                &self.weapon,
+                self.kind,
+                penalty,
+                self.infraction,
+            )
             .await;
+
+        let Some(victim_text) = victim_message(penalty, self.kind, &self.killer.name, &self.weapon)
+        else {
+            return Ok(());
+        };
+
+        transceiver
+            .message_player(&self.victim.id, &victim_text)
+            .await?;
+        Ok(())
     }
 }
 
-fn is_weapon_melee(name: &str) -> bool {
-    let lower = name.to_lowercase();
-    let keywords = vec!["knife", "shovel", "spaten", "spade", "sykes"];
+/// Classify a kill by the categories of its weapon. Only kills with nothing but a vehicle or
+/// the environment are roadkills or environmental, mounted vehicle weapons are regular weapons.
+fn kill_kind(categories: &[WeaponCategory], suicide: bool, is_teamkill: bool) -> KillKind {
+    let only = |category| !categories.is_empty() && categories.iter().all(|c| *c == category);
+
+    if suicide {
+        KillKind::Suicide
+    } else if only(WeaponCategory::Environment) {
+        KillKind::Environment
+    } else if only(WeaponCategory::Vehicle) {
+        KillKind::Roadkill
+    } else if is_teamkill {
+        KillKind::Teamkill
+    } else {
+        KillKind::Enemy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::weapons::WeaponCatalogue;
+
+    fn kind(weapon: &str, suicide: bool, is_teamkill: bool) -> KillKind {
+        let categories = WeaponCatalogue::default().categories(weapon);
+        kill_kind(&categories, suicide, is_teamkill)
+    }
+
+    #[test]
+    fn vehicle_only_kills_are_roadkills() {
+        assert_eq!(kind("Jeep Willys", false, false), KillKind::Roadkill);
+        assert_eq!(kind("Jeep Willys", false, true), KillKind::Roadkill);
+    }
+
+    #[test]
+    fn mounted_vehicle_weapons_are_regular_kills() {
+        assert_eq!(
+            kind("COAXIAL M1919 [Sherman M4A3(75)W]", false, false),
+            KillKind::Enemy
+        );
+        assert_eq!(
+            kind("COAXIAL M1919 [Sherman M4A3(75)W]", false, true),
+            KillKind::Teamkill
+        );
+    }
 
-    keywords.iter().any(|word| lower.contains(word))
+    #[test]
+    fn environment_kills_are_environmental() {
+        assert_eq!(kind("UNKNOWN", false, false), KillKind::Environment);
+        assert_eq!(kind("UNKNOWN", true, false), KillKind::Suicide);
+    }
+
+    #[test]
+    fn unknown_weapons_are_regular_kills() {
+        assert_eq!(kind("Something New", false, false), KillKind::Enemy);
+    }
 }
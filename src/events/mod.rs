@@ -1,34 +1,571 @@
-use crate::{client::WsTransceiver, manage::command::StartEvent};
+use std::{
+    any::Any,
+    fmt::Display,
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex as SyncMutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    checkpoint::{Checkpoint, Checkpoints, EventState},
+    client::{ClientError, WsTransceiver, WsTransceiverExt},
+    history::{EventRecorder, History},
+    manage::command::StartEvent,
+    penalties::PenaltyLadder,
+    weapons::WeaponCatalogue,
+};
+use chrono::{DateTime, Utc};
 use derive_new::new;
+use futures_util::FutureExt;
+use log::{error, info, warn};
 use melee_mania::*;
 use radio_spies::RadioSpies;
+use serde::Deserialize;
 use sky_eye::*;
+
+pub use sky_eye::revoke_orphaned_cams;
+use tokio::{
+    sync::Notify,
+    task::JoinHandle,
+    time::{self, timeout},
+};
 use tokio_util::sync::CancellationToken;
+use wise_api::rcon::parsing::Player;
 
 mod melee_mania;
 mod radio_spies;
 mod sky_eye;
 
-pub trait Event {
-    fn start(&self);
-    fn stop(&self);
+/// How often the state of a running event is saved to disk.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A part of the game an event takes control of. Events claiming the same part conflict with
+/// each other and never run at the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Claim {
+    /// Penalizing players for their kills.
+    Kills,
+    /// Handing out admin cam access.
+    AdminCams,
+    /// Relaying chat messages between the teams.
+    Chat,
+}
+
+/// The parts of the game the event would take control of.
+pub fn claims(start: &StartEvent) -> &'static [Claim] {
+    match start {
+        StartEvent::MeleeMania { .. } | StartEvent::RestrictedArsenal { .. } => melee_mania::CLAIMS,
+        StartEvent::SkyEye { .. } => sky_eye::CLAIMS,
+        StartEvent::RadioSpies {} => radio_spies::CLAIMS,
+    }
+}
+
+/// Whether two events claim the same part of the game.
+pub fn conflicts(start: &StartEvent, other: &StartEvent) -> bool {
+    claims(start)
+        .iter()
+        .any(|claim| claims(other).contains(claim))
+}
+
+/// A mini game. The lifecycle is driven by an [`EventHandle`] in the following order:
+/// [`Event::prepare`], [`Event::run`], [`Event::on_stop`] if the event was stopped early and
+/// finally [`Event::finished`]. Should the lifecycle panic, [`Event::finished`] is still called
+/// on a fresh copy of the event so everything it handed out can be taken back.
+#[async_trait::async_trait]
+pub trait Event: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Get the event ready, for example by announcing it. Failing aborts the event before it runs.
+    async fn prepare(&mut self) -> Result<(), ClientError> {
+        Ok(())
+    }
+
+    /// Enforce the event until it ends on its own or its token gets cancelled.
+    async fn run(&mut self);
+
+    /// Called when the event has been stopped before it could end on its own. If the event had
+    /// to be aborted, this is called on another copy of the event instead and is all the clean
+    /// up it gets.
+    async fn on_stop(&mut self) {}
+
+    /// Called once the event is over, regardless of how it ended.
+    async fn finished(&mut self) {}
+
+    /// Report the current state of the event.
+    async fn status(&self) -> EventStatus;
+
+    /// Capture what is needed to resume the event after a restart.
+    async fn state(&self) -> EventState {
+        EventState::Stateless
+    }
+
+    /// Pick up the state of an event interrupted by a restart. Called before the lifecycle
+    /// starts, events should not announce themselves again afterwards.
+    async fn restore(&mut self, _state: EventState) {}
+
+    /// Called when the match ends while the event carries over into the next one. Nothing
+    /// should be enforced until [`Event::resume`] is called.
+    async fn pause(&self) {}
+
+    /// Called when the next match starts after the event has been paused.
+    async fn resume(&self) {}
+
+    /// Reset the infractions of the player with the given name or id.
+    async fn forgive(&self, _player: &str) -> Forgiveness {
+        Forgiveness::Unsupported
+    }
+}
+
+/// What happens to a running event when its match ends.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MatchEndPolicy {
+    /// End the event as if an admin had ended it.
+    #[default]
+    Stop,
+    /// Pause the event and continue it once the next match starts.
+    CarryOver,
+}
+
+/// Configures for every event what happens when the match ends.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default)]
+pub struct MatchEndConfig {
+    pub melee_mania: MatchEndPolicy,
+    pub restricted_arsenal: MatchEndPolicy,
+    pub sky_eye: MatchEndPolicy,
+    pub radio_spies: MatchEndPolicy,
+}
+
+impl MatchEndConfig {
+    pub fn policy(&self, start: &StartEvent) -> MatchEndPolicy {
+        match start {
+            StartEvent::MeleeMania { .. } => self.melee_mania,
+            StartEvent::RestrictedArsenal { .. } => self.restricted_arsenal,
+            StartEvent::SkyEye { .. } => self.sky_eye,
+            StartEvent::RadioSpies {} => self.radio_spies,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Forgiveness {
+    Forgiven(Player),
+    /// The player has no infractions during the event.
+    UnknownPlayer,
+    /// The event does not track infractions.
+    Unsupported,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventPhase {
+    /// The event has been announced but is not enforced yet.
+    Announced,
+    Running,
+    /// The match has ended and the event waits for the next one.
+    Paused,
+    Ended,
+}
+
+impl Display for EventPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let phase = match self {
+            EventPhase::Announced => "Announced",
+            EventPhase::Running => "Running",
+            EventPhase::Paused => "Paused until the next match",
+            EventPhase::Ended => "Ended",
+        };
+        f.write_str(phase)
+    }
+}
+
+#[derive(new, Debug, Clone)]
+pub struct EventStatus {
+    pub name: &'static str,
+    pub phase: EventPhase,
+
+    /// Time until the event moves on to its next phase, if known.
+    pub remaining: Option<Duration>,
+
+    /// Event specific counters such as handed out infractions.
+    pub counters: Vec<(&'static str, u64)>,
+}
+
+impl Display for EventStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}: {}", self.name, self.phase)?;
+
+        if let Some(remaining) = self.remaining {
+            // Drop the sub second part, nobody needs to know the nanoseconds left.
+            let remaining = Duration::from_secs(remaining.as_secs());
+            let label = match self.phase {
+                EventPhase::Announced => "Starts in",
+                _ => "Remaining",
+            };
+            writeln!(f, "{}: {}", label, humantime::format_duration(remaining))?;
+        }
+
+        for (name, value) in &self.counters {
+            writeln!(f, "{}: {}", name, value)?;
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(new, Clone)]
 pub struct EventContext {
     pub transceiver: WsTransceiver,
     pub token: CancellationToken,
+    pub weapons: Arc<WeaponCatalogue>,
+    pub penalties: Arc<PenaltyLadder>,
+    pub history: EventRecorder,
+    pub checkpoints: Checkpoints,
+}
+
+/// Whether an event is paused between matches, shared by every copy of the event so pausing
+/// affects the running one.
+#[derive(Debug, Clone, Default)]
+pub struct PauseState {
+    pause: Arc<SyncMutex<Pause>>,
+
+    /// Wakes the running event up when it gets paused or resumed.
+    changed: Arc<Notify>,
+}
+
+#[derive(Debug, Default)]
+struct Pause {
+    since: Option<Instant>,
+    /// Time spent in earlier pauses.
+    total: Duration,
+}
+
+impl PauseState {
+    pub fn is_paused(&self) -> bool {
+        self.pause.lock().unwrap().since.is_some()
+    }
+
+    /// How long the event has been paused so far, including the current pause.
+    pub fn paused_for(&self) -> Duration {
+        let pause = self.pause.lock().unwrap();
+        pause.total + pause.since.map_or(Duration::ZERO, |since| since.elapsed())
+    }
+
+    /// Returns whether the event was running until now.
+    pub fn pause(&self, name: &str) -> bool {
+        let mut pause = self.pause.lock().unwrap();
+        if pause.since.is_some() {
+            return false;
+        }
+
+        info!("Pausing {} until the next match", name);
+        pause.since = Some(Instant::now());
+        self.changed.notify_one();
+        true
+    }
+
+    /// Returns whether the event was paused until now.
+    pub fn resume(&self, name: &str) -> bool {
+        let mut pause = self.pause.lock().unwrap();
+        let Some(since) = pause.since.take() else {
+            return false;
+        };
+
+        info!("Resuming {} in the new match", name);
+        pause.total += since.elapsed();
+        self.changed.notify_one();
+        true
+    }
+
+    /// Wait until the event gets paused or resumed.
+    pub async fn changed(&self) {
+        self.changed.notified().await
+    }
+}
+
+/// A running event and the task driving it.
+pub struct EventHandle {
+    event: Box<dyn Event>,
+    start: StartEvent,
+    token: CancellationToken,
+    task: JoinHandle<()>,
+    checkpoints: Checkpoints,
+
+    /// The history run, which also identifies the checkpoint of the event.
+    run: String,
+
+    /// When the event was first started, kept across restarts.
+    started: DateTime<Utc>,
 }
 
-pub fn build_event(transceiver: WsTransceiver, start: &StartEvent) -> Box<dyn Event> {
-    let ctx = EventContext::new(transceiver, Default::default());
+impl EventHandle {
+    /// Drive a copy of `event` through its lifecycle on a new task, record the run in the
+    /// history and keep a checkpoint of it while it runs. `resumed` is when the event was first
+    /// started if it is picked up again after a restart.
+    fn spawn<E: Event + Clone + 'static>(
+        event: E,
+        ctx: EventContext,
+        start: &StartEvent,
+        resumed: Option<DateTime<Utc>>,
+    ) -> Self {
+        let started = resumed.unwrap_or_else(Utc::now);
+        let runner = event.clone();
+        let mut recovery = event.clone();
+        let token = ctx.token.clone();
+        let checkpoints = ctx.checkpoints.clone();
+        let start = start.clone();
+        let config = start.clone();
+        let run = ctx.history.run().to_string();
+        let task = tokio::spawn(async move {
+            if resumed.is_none() {
+                ctx.history.started(recovery.name(), &start).await;
+            }
+
+            let lifecycle = AssertUnwindSafe(drive(runner, ctx.token.clone())).catch_unwind();
+            tokio::pin!(lifecycle);
+            let mut checkpoint = time::interval(CHECKPOINT_INTERVAL);
+            checkpoint.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
+
+            let result = loop {
+                tokio::select! {
+                    result = &mut lifecycle => break result,
+                    _ = checkpoint.tick() => {
+                        let checkpoint = Checkpoint {
+                            run: ctx.history.run().to_string(),
+                            started,
+                            config: start.clone(),
+                            state: recovery.state().await,
+                        };
+                        ctx.checkpoints.save(&checkpoint).await;
+                    }
+                }
+            };
+
+            if let Err(panic) = result {
+                error!(
+                    "Event {} panicked -> Cleaning up: {}",
+                    recovery.name(),
+                    panic_message(&*panic)
+                );
+                recovery.finished().await;
+            }
+
+            let mut transceiver = ctx.transceiver;
+            let participants = match transceiver.get_players().await {
+                Ok(players) => players,
+                Err(e) => {
+                    warn!("Failed to get participants for the history: {}", e);
+                    Vec::new()
+                }
+            };
+            ctx.history
+                .ended(participants, &recovery.status().await)
+                .await;
+            ctx.checkpoints.clear(ctx.history.run()).await;
+        });
+
+        Self {
+            event: Box::new(event),
+            start: config,
+            token,
+            task,
+            checkpoints,
+            run,
+            started,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.event.name()
+    }
+
+    pub async fn status(&self) -> EventStatus {
+        self.event.status().await
+    }
+
+    /// The command the event was started with.
+    pub fn start(&self) -> &StartEvent {
+        &self.start
+    }
+
+    /// How long the event has been running, including its announcement.
+    pub fn running_for(&self) -> Duration {
+        (Utc::now() - self.started)
+            .to_std()
+            .unwrap_or(Duration::ZERO)
+    }
+
+    /// Whether the event cannot run at the same time as `other`.
+    pub fn conflicts_with(&self, other: &StartEvent) -> bool {
+        conflicts(&self.start, other)
+    }
+
+    pub async fn forgive(&self, player: &str) -> Forgiveness {
+        self.event.forgive(player).await
+    }
+
+    pub async fn pause(&self) {
+        self.event.pause().await
+    }
+
+    pub async fn resume(&self) {
+        self.event.resume().await
+    }
+
+    /// Whether the event has run through its whole lifecycle.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Stop the event and wait up to `grace` for it to shut down, abort it afterwards.
+    pub async fn stop(mut self, grace: Duration) {
+        self.token.cancel();
+
+        match timeout(grace, &mut self.task).await {
+            Ok(Ok(())) => info!("Event {} has shut down", self.name()),
+            Ok(Err(e)) => error!("Event {} failed: {}", self.name(), e),
+            Err(_) => {
+                warn!(
+                    "Event {} did not shut down within {} -> Aborting",
+                    self.name(),
+                    humantime::format_duration(grace)
+                );
+                self.task.abort();
+                self.event.on_stop().await;
+                // The event was stopped on purpose, it must not come back after a restart.
+                self.checkpoints.clear(&self.run).await;
+            }
+        }
+    }
+}
+
+async fn drive<E: Event>(mut event: E, token: CancellationToken) {
+    match event.prepare().await {
+        Ok(()) if !token.is_cancelled() => event.run().await,
+        Ok(()) => {}
+        Err(e) => error!("Failed to prepare {} -> Aborting: {}", event.name(), e),
+    }
+
+    if token.is_cancelled() {
+        event.on_stop().await;
+    }
+
+    event.finished().await;
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+pub fn build_event(
+    transceiver: WsTransceiver,
+    weapons: Arc<WeaponCatalogue>,
+    penalties: Arc<PenaltyLadder>,
+    history: &History,
+    checkpoints: Checkpoints,
+    start: &StartEvent,
+) -> EventHandle {
+    let ctx = EventContext::new(
+        transceiver,
+        Default::default(),
+        weapons,
+        penalties,
+        history.recorder(),
+        checkpoints,
+    );
+
+    match start {
+        StartEvent::MeleeMania { .. } | StartEvent::RestrictedArsenal { .. } => EventHandle::spawn(
+            MeleeMania::new(MeleeManiaConfig::from_config(start), ctx.clone()),
+            ctx,
+            start,
+            None,
+        ),
+        StartEvent::SkyEye { .. } => EventHandle::spawn(
+            SkyEye::new(SkyEyeConfig::from_config(start), ctx.clone()),
+            ctx,
+            start,
+            None,
+        ),
+        StartEvent::RadioSpies {} => {
+            EventHandle::spawn(RadioSpies::new(ctx.clone()), ctx, start, None)
+        }
+    }
+}
+
+/// Rebuild the event of a checkpoint left behind by a previous run of hellvents.
+pub async fn resume_event(
+    transceiver: WsTransceiver,
+    weapons: Arc<WeaponCatalogue>,
+    penalties: Arc<PenaltyLadder>,
+    history: &History,
+    checkpoints: Checkpoints,
+    checkpoint: Checkpoint,
+) -> EventHandle {
+    let ctx = EventContext::new(
+        transceiver,
+        Default::default(),
+        weapons,
+        penalties,
+        history.resume(checkpoint.run),
+        checkpoints,
+    );
+    let start = &checkpoint.config;
 
     match start {
-        StartEvent::MeleeMania { .. } => Box::new(MeleeMania::new(
-            MeleeManiaConfig::from_config(start),
-            ctx.transceiver,
-        )),
-        StartEvent::SkyEye { .. } => Box::new(SkyEye::new(SkyEyeConfig::from_config(start), ctx)),
-        StartEvent::RadioSpies {} => Box::new(RadioSpies::new(ctx)),
+        StartEvent::MeleeMania { .. } | StartEvent::RestrictedArsenal { .. } => {
+            let mut event = MeleeMania::new(MeleeManiaConfig::from_config(start), ctx.clone());
+            event.restore(checkpoint.state).await;
+            EventHandle::spawn(event, ctx, start, Some(checkpoint.started))
+        }
+        StartEvent::SkyEye { .. } => {
+            let mut event = SkyEye::new(SkyEyeConfig::from_config(start), ctx.clone());
+            event.restore(checkpoint.state).await;
+            EventHandle::spawn(event, ctx, start, Some(checkpoint.started))
+        }
+        StartEvent::RadioSpies {} => EventHandle::spawn(
+            RadioSpies::new(ctx.clone()),
+            ctx,
+            start,
+            Some(checkpoint.started),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pause_state_reports_changes_only() {
+        let pause = PauseState::default();
+
+        assert!(!pause.resume("Test"));
+        assert!(pause.pause("Test"));
+        assert!(!pause.pause("Test"));
+        assert!(pause.is_paused());
+        assert!(pause.resume("Test"));
+        assert!(!pause.is_paused());
+    }
+
+    #[test]
+    fn pause_state_adds_up_pauses() {
+        let pause = PauseState::default();
+        assert_eq!(pause.paused_for(), Duration::ZERO);
+
+        pause.pause("Test");
+        std::thread::sleep(Duration::from_millis(10));
+        pause.resume("Test");
+        let first = pause.paused_for();
+        assert!(first >= Duration::from_millis(10));
+
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(pause.paused_for(), first);
     }
 }
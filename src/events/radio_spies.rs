@@ -10,17 +10,22 @@ The victim can send a message to the killer.
 CHAT
 Messages sent in ALL chat are transmitted to the other side in 30s intervals.
 Messages sent in UNIT chat are transmitted to the corresponding unit in 10s intervals.
+Between matches the event is paused, nothing is intercepted and messages from the last match are dropped.
 */
 
 use std::{
     collections::HashMap,
     fmt::Display,
     str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
 use derive_new::new;
-use log::{debug, info};
+use log::{debug, error, info, warn};
 use tokio::time::{self};
 use wise_api::{
     events::RconEvent,
@@ -30,34 +35,32 @@ use wise_api::{
 
 use crate::{client::WsTransceiverExt, utils::get_players_with_team};
 
-use super::{Event, EventContext};
+use super::{Claim, Event, EventContext, EventPhase, EventStatus, PauseState};
+
+pub const CLAIMS: &[Claim] = &[Claim::Chat];
 
 #[derive(Clone)]
 pub struct RadioSpies {
     ctx: EventContext,
     messages: HashMap<String, Vec<CachedMessage>>,
-}
 
-impl Event for RadioSpies {
-    fn start(&self) {
-        let clone = self.clone();
-        _ = tokio::spawn(clone.run());
-    }
+    /// Messages intercepted from chat so far.
+    intercepted: Arc<AtomicU64>,
 
-    fn stop(&self) {
-        self.ctx.token.cancel();
-    }
+    /// Times intercepted messages have been handed to the other team.
+    flushes: Arc<AtomicU64>,
+
+    /// Set while the event is paused between matches, nothing is relayed in the meantime.
+    pause: PauseState,
 }
 
-impl RadioSpies {
-    pub fn new(ctx: EventContext) -> Self {
-        Self {
-            ctx,
-            messages: HashMap::new(),
-        }
+#[async_trait::async_trait]
+impl Event for RadioSpies {
+    fn name(&self) -> &'static str {
+        "Radio Spies"
     }
 
-    async fn run(mut self) {
+    async fn run(&mut self) {
         info!("Enforcing Radio Spies");
         let mut interval = time::interval(Duration::from_secs(30));
         interval.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
@@ -70,11 +73,28 @@ impl RadioSpies {
                     return;
                 }
 
-                _ = interval.tick() => {
+                _ = interval.tick(), if !self.pause.is_paused() => {
                     self.flush_cached_messages().await;
                 }
 
+                _ = self.pause.changed() => {
+                    if self.pause.is_paused() {
+                        // Intelligence about the last match is of no use in the next one.
+                        self.messages.clear();
+                    } else {
+                        interval.reset();
+                    }
+                }
+
                 message = self.ctx.transceiver.receive() => {
+                    let message = match message {
+                        Ok(message) => message,
+                        Err(e) => {
+                            error!("Failed to receive message -> Stopping: {}", e);
+                            return;
+                        }
+                    };
+
                     let ServerWsMessage::Rcon(event) = message else {
                         continue;
                     };
@@ -85,13 +105,57 @@ impl RadioSpies {
         }
     }
 
+    async fn status(&self) -> EventStatus {
+        let phase = if self.ctx.token.is_cancelled() {
+            EventPhase::Ended
+        } else if self.pause.is_paused() {
+            EventPhase::Paused
+        } else {
+            EventPhase::Running
+        };
+
+        let counters = vec![
+            ("Intercepted", self.intercepted.load(Ordering::Relaxed)),
+            ("Deliveries", self.flushes.load(Ordering::Relaxed)),
+        ];
+
+        EventStatus::new(self.name(), phase, None, counters)
+    }
+
+    async fn pause(&self) {
+        self.pause.pause(self.name());
+    }
+
+    async fn resume(&self) {
+        self.pause.resume(self.name());
+    }
+}
+
+impl RadioSpies {
+    pub fn new(ctx: EventContext) -> Self {
+        Self {
+            ctx,
+            messages: HashMap::new(),
+            intercepted: Arc::default(),
+            flushes: Arc::default(),
+            pause: PauseState::default(),
+        }
+    }
+
     async fn flush_cached_messages(&mut self) {
         let mut team_messages = HashMap::new();
         for (team, messages) in &self.messages {
             team_messages.insert(team, build_collected_message(messages));
         }
 
-        let player_teams = get_players_with_team(&mut self.ctx.transceiver).await;
+        let player_teams = match get_players_with_team(&mut self.ctx.transceiver).await {
+            Ok(player_teams) => player_teams,
+            Err(e) => {
+                warn!("Failed to get players, keeping messages cached: {}", e);
+                return;
+            }
+        };
+
         for (player, team) in player_teams {
             let Some(opposite_team) = opposite_team(&team) else {
                 continue;
@@ -101,19 +165,29 @@ impl RadioSpies {
                 continue;
             };
 
-            self.ctx
+            if let Err(e) = self
+                .ctx
                 .transceiver
                 .message_player(&player.id, &message)
-                .await;
+                .await
+            {
+                warn!("Failed to send intelligence to {:?}: {}", player.id, e);
+            }
         }
 
         self.messages.clear();
         self.messages.insert("Allies".to_string(), Vec::new());
         self.messages.insert("Axis".to_string(), Vec::new());
+        self.flushes.fetch_add(1, Ordering::Relaxed);
         debug!("Flushed cached messages");
     }
 
     async fn handle_rcon_event(&mut self, event: RconEvent) {
+        // Nothing is intercepted between matches.
+        if self.pause.is_paused() {
+            return;
+        }
+
         let RconEvent::Log(LogLine {
             timestamp: _,
             kind:
@@ -133,6 +207,7 @@ impl RadioSpies {
             .entry(team)
             .or_insert_with(Vec::new)
             .push(cached.clone());
+        self.intercepted.fetch_add(1, Ordering::Relaxed);
         debug!("Cached message {:?}", cached);
     }
 }
@@ -1,25 +1,69 @@
-use std::{collections::HashMap, sync::Arc};
+/*
+CONCEPT
+Give a few random players of each team a bird's eye view of the battlefield.
 
+CAMERAS
+Every team receives admin cam access for a number of its players.
+Holders rotate on a fixed interval, holders who leave or switch teams are replaced right away.
+When the event ends all granted cameras are revoked again.
+Between matches the event is paused, all cameras are revoked and handed out anew once the next match starts.
+Players who already are admins never receive a camera, so their access is never touched.
+*/
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use log::{debug, error, info, warn};
 use rand::{seq::IteratorRandom, thread_rng};
-use tokio::sync::Mutex;
-use wise_api::rcon::parsing::{Player, PlayerId};
+use tokio::{sync::Mutex, time};
+use wise_api::{
+    events::RconEvent,
+    messages::ServerWsMessage,
+    rcon::parsing::{
+        showlog::{LogKind, LogLine},
+        Player, PlayerId,
+    },
+};
+
+use crate::{
+    checkpoint::EventState,
+    client::{ClientError, WsTransceiver, WsTransceiverExt},
+    manage::command::StartEvent,
+    messages::sky_eye::*,
+    utils::get_players_with_team,
+};
+
+use super::{Claim, Event, EventContext, EventPhase, EventStatus, PauseState};
 
-use crate::{manage::command::StartEvent, utils::get_players_with_team};
+pub const CLAIMS: &[Claim] = &[Claim::AdminCams];
 
-use super::{Event, EventContext};
+/// How often holders are checked for having left or switched teams.
+const HOLDER_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The comment on every admin entry granted by the event, used to recognize them after a restart.
+const CAM_COMMENT: &str = "Hellvents Sky Eye";
 
 pub struct SkyEyeConfig {
     cam_count: usize,
+    rotation: Duration,
 }
 
 impl SkyEyeConfig {
     pub fn from_config(config: &StartEvent) -> Self {
-        let StartEvent::SkyEye { cam_count } = config else {
+        let StartEvent::SkyEye {
+            cam_count,
+            rotation,
+        } = config
+        else {
             panic!("Tried to build config from invalid start event");
         };
 
         Self {
             cam_count: *cam_count,
+            rotation: *rotation,
         }
     }
 }
@@ -28,18 +72,178 @@ impl SkyEyeConfig {
 pub struct SkyEye {
     config: Arc<SkyEyeConfig>,
     ctx: EventContext,
-    #[allow(dead_code)]
-    camers: Arc<Mutex<Vec<PlayerId>>>,
+
+    /// Players currently holding admin cam access granted by the event with the team they were
+    /// granted it for. Only these are ever revoked.
+    camers: Arc<Mutex<HashMap<PlayerId, (Player, String)>>>,
+
+    /// Set while the event is paused between matches, no cameras are held in the meantime.
+    pause: PauseState,
+
+    /// Whether the event was picked up again after a restart and has already been announced.
+    resumed: bool,
 }
 
+#[async_trait::async_trait]
 impl Event for SkyEye {
-    fn start(&self) {
-        let clone = self.clone();
-        _ = tokio::spawn(clone.run());
+    fn name(&self) -> &'static str {
+        "Sky Eye"
+    }
+
+    async fn prepare(&mut self) -> Result<(), ClientError> {
+        if self.resumed {
+            return Ok(());
+        }
+
+        let announce_start = start_message(self.config.cam_count, &self.config.rotation);
+        self.ctx
+            .transceiver
+            .broadcast_message(&announce_start)
+            .await
+    }
+
+    async fn run(&mut self) {
+        info!(
+            "Enforcing Sky Eye with {} cameras per team rotating every {}",
+            self.config.cam_count,
+            humantime::format_duration(self.config.rotation)
+        );
+
+        // The first tick completes immediately which hands out the initial cameras.
+        let mut rotation = time::interval(self.config.rotation);
+        rotation.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
+        let mut holder_check = time::interval(HOLDER_CHECK_INTERVAL);
+        holder_check.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
+        holder_check.reset();
+
+        loop {
+            tokio::select! {
+                _ = self.ctx.token.cancelled() => {
+                    info!("Cancellation detected -> Stopping");
+                    return;
+                }
+
+                _ = rotation.tick(), if !self.pause.is_paused() => {
+                    self.rotate_camers().await;
+                }
+
+                _ = holder_check.tick(), if !self.pause.is_paused() => {
+                    self.replace_invalid_camers().await;
+                }
+
+                _ = self.pause.changed() => {
+                    if self.pause.is_paused() {
+                        self.revoke_all().await;
+                    } else {
+                        // Hand out cameras to the players of the new match right away.
+                        rotation.reset_immediately();
+                        holder_check.reset();
+                    }
+                }
+
+                message = self.ctx.transceiver.receive() => {
+                    let message = match message {
+                        Ok(message) => message,
+                        Err(e) => {
+                            error!("Failed to receive message -> Stopping: {}", e);
+                            return;
+                        }
+                    };
+
+                    let ServerWsMessage::Rcon(event) = message else {
+                        continue;
+                    };
+
+                    self.handle_rcon_event(event).await;
+                }
+            }
+        }
     }
 
-    fn stop(&self) {
-        todo!()
+    /// Cameras are taken back right away, which is all that happens if the event gets aborted.
+    async fn on_stop(&mut self) {
+        self.revoke_all().await;
+    }
+
+    async fn finished(&mut self) {
+        self.revoke_all().await;
+
+        if let Err(e) = self.ctx.transceiver.broadcast_message(&end_message()).await {
+            warn!("Failed to broadcast end message: {}", e);
+        }
+    }
+
+    async fn state(&self) -> EventState {
+        let camers = self.camers.lock().await.values().cloned().collect();
+        EventState::SkyEye {
+            camers,
+            paused: self.pause.is_paused(),
+        }
+    }
+
+    /// Holders from before the restart are taken over so the first rotation revokes them. Holders
+    /// whose admin entry was changed in the meantime are no longer ours to revoke.
+    async fn restore(&mut self, state: EventState) {
+        let EventState::SkyEye { camers, paused } = state else {
+            warn!("Cannot restore {} from {:?}", self.name(), state);
+            return;
+        };
+
+        let admins = match self.ctx.transceiver.get_admins().await {
+            Ok(admins) => Some(admins),
+            Err(e) => {
+                warn!("Failed to get admins, taking over all holders: {}", e);
+                None
+            }
+        };
+
+        let mut guard = self.camers.lock().await;
+        for (player, team) in camers {
+            let id = player.id.to_string();
+            let granted = admins.as_ref().is_none_or(|admins| {
+                admins
+                    .iter()
+                    .any(|admin| admin.id == id && admin.comment == CAM_COMMENT)
+            });
+
+            if !granted {
+                info!(
+                    "{:?} no longer holds a Sky Eye camera -> Not taking over",
+                    player
+                );
+                continue;
+            }
+
+            guard.insert(player.id.clone(), (player, team));
+        }
+
+        // Holders left over from before the pause are revoked as soon as the event runs.
+        if paused {
+            self.pause.pause(self.name());
+        }
+        self.resumed = true;
+    }
+
+    async fn status(&self) -> EventStatus {
+        let phase = if self.ctx.token.is_cancelled() {
+            EventPhase::Ended
+        } else if self.pause.is_paused() {
+            EventPhase::Paused
+        } else {
+            EventPhase::Running
+        };
+
+        let counters = vec![("Cameras granted", self.camers.lock().await.len() as u64)];
+
+        EventStatus::new(self.name(), phase, None, counters)
+    }
+
+    async fn pause(&self) {
+        self.pause.pause(self.name());
+    }
+
+    async fn resume(&self) {
+        self.pause.resume(self.name());
     }
 }
 
@@ -49,20 +253,220 @@ impl SkyEye {
             config: Arc::new(config),
             ctx,
             camers: Arc::default(),
+            pause: PauseState::default(),
+            resumed: false,
         }
     }
 
-    pub async fn run(mut self) {
-        let player_teams = get_players_with_team(&mut self.ctx.transceiver).await;
-        let _selected = select_random_players(self.config.cam_count, player_teams);
+    async fn handle_rcon_event(&mut self, event: RconEvent) {
+        // Nobody holds a camera between matches.
+        if self.pause.is_paused() {
+            return;
+        }
 
-        loop {}
+        let RconEvent::Log(LogLine {
+            timestamp: _,
+            kind:
+                LogKind::Connect {
+                    player,
+                    connect: false,
+                },
+        }) = event
+        else {
+            return;
+        };
+
+        if self.camers.lock().await.contains_key(&player.id) {
+            debug!("Camera holder {:?} disconnected", player);
+            self.replace_invalid_camers().await;
+        }
+    }
+
+    /// Revoke all cameras and hand them to new players, preferring those who did not have one.
+    async fn rotate_camers(&mut self) {
+        let player_teams = match get_players_with_team(&mut self.ctx.transceiver).await {
+            Ok(player_teams) => player_teams,
+            Err(e) => {
+                warn!("Failed to get players, skipping rotation: {}", e);
+                return;
+            }
+        };
+
+        let previous: HashSet<PlayerId> = self.camers.lock().await.keys().cloned().collect();
+        self.revoke_all().await;
+        self.fill_camers(player_teams, &previous).await;
+    }
+
+    /// Revoke the cameras of holders who left or switched teams and replace them.
+    async fn replace_invalid_camers(&mut self) {
+        let player_teams = match get_players_with_team(&mut self.ctx.transceiver).await {
+            Ok(player_teams) => player_teams,
+            Err(e) => {
+                warn!("Failed to get players, skipping holder check: {}", e);
+                return;
+            }
+        };
+
+        let current_teams: HashMap<&PlayerId, &String> = player_teams
+            .iter()
+            .map(|(player, team)| (&player.id, team))
+            .collect();
+
+        let invalid: HashSet<PlayerId> = self
+            .camers
+            .lock()
+            .await
+            .iter()
+            .filter(|(id, (_, team))| current_teams.get(id) != Some(&team))
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        if invalid.is_empty() {
+            return;
+        }
+
+        for id in &invalid {
+            self.revoke(id).await;
+        }
+
+        self.fill_camers(player_teams, &invalid).await;
+    }
+
+    /// Grant cameras until every team has its share of holders and announce the new holders.
+    /// Players who already are admins are never picked.
+    async fn fill_camers(
+        &mut self,
+        player_teams: Vec<(Player, String)>,
+        avoid: &HashSet<PlayerId>,
+    ) {
+        let admins: HashSet<String> = match self.ctx.transceiver.get_admins().await {
+            Ok(admins) => admins.into_iter().map(|admin| admin.id).collect(),
+            Err(e) => {
+                warn!("Failed to get admins, skipping granting cameras: {}", e);
+                return;
+            }
+        };
+
+        let (missing, candidates) = {
+            let guard = self.camers.lock().await;
+
+            let mut missing: HashMap<String, usize> = HashMap::new();
+            for (_, team) in &player_teams {
+                let holding = guard.values().filter(|(_, t)| t == team).count();
+                missing.insert(team.clone(), self.config.cam_count.saturating_sub(holding));
+            }
+
+            let candidates: Vec<(Player, String)> = player_teams
+                .into_iter()
+                .filter(|(player, _)| {
+                    !guard.contains_key(&player.id) && !admins.contains(&player.id.to_string())
+                })
+                .collect();
+
+            (missing, candidates)
+        };
+
+        let selected = select_random_players(&missing, candidates, avoid);
+        if selected.is_empty() {
+            return;
+        }
+
+        let mut granted = Vec::new();
+        for (player, team) in selected {
+            match self
+                .ctx
+                .transceiver
+                .grant_admin_cam(&player.id, CAM_COMMENT)
+                .await
+            {
+                Ok(outcome) if outcome.is_executed() => {
+                    debug!("Granted camera to {:?} of {}", player, team);
+                    self.camers
+                        .lock()
+                        .await
+                        .insert(player.id.clone(), (player.clone(), team.clone()));
+                    granted.push((player, team));
+                }
+                Ok(_) => warn!("Server rejected camera for {:?}", player),
+                Err(e) => warn!("Failed to grant camera to {:?}: {}", player, e),
+            }
+        }
+
+        if granted.is_empty() {
+            return;
+        }
+
+        let message = granted_message(&granted);
+        if let Err(e) = self.ctx.transceiver.broadcast_message(&message).await {
+            warn!("Failed to announce camera holders: {}", e);
+        }
+    }
+
+    /// Revoke the camera of a holder. The holder is only forgotten once the camera is gone, so
+    /// failed attempts are retried by the next holder check or rotation.
+    async fn revoke(&mut self, id: &PlayerId) {
+        match self.ctx.transceiver.revoke_admin_cam(id).await {
+            Ok(outcome) if outcome.is_executed() => {
+                debug!("Revoked camera of {:?}", id);
+                self.camers.lock().await.remove(id);
+            }
+            Ok(_) => warn!("Server rejected revoking camera of {:?}", id),
+            Err(e) => error!("Failed to revoke camera of {:?}: {}", id, e),
+        }
+    }
+
+    async fn revoke_all(&mut self) {
+        let ids: Vec<PlayerId> = self.camers.lock().await.keys().cloned().collect();
+        for id in ids {
+            self.revoke(&id).await;
+        }
+    }
+}
+
+/// Revoke the cameras Sky Eye granted that none of the resumed events holds, e.g. cameras granted
+/// after the last checkpoint or left behind by an emergency exit.
+pub async fn revoke_orphaned_cams(mut transceiver: WsTransceiver, resumed: &[EventState]) {
+    let holders: HashSet<String> = resumed
+        .iter()
+        .filter_map(|state| match state {
+            EventState::SkyEye { camers, .. } => Some(camers),
+            _ => None,
+        })
+        .flatten()
+        .map(|(player, _)| player.id.to_string())
+        .collect();
+
+    let admins = match transceiver.get_admins().await {
+        Ok(admins) => admins,
+        Err(e) => {
+            warn!("Failed to get admins, not revoking leftover cameras: {}", e);
+            return;
+        }
+    };
+
+    for admin in admins {
+        if admin.comment != CAM_COMMENT || holders.contains(&admin.id) {
+            continue;
+        }
+
+        match transceiver
+            .request_raw(format!("AdminDel {}", admin.id))
+            .await
+        {
+            Ok(outcome) if outcome.is_executed() => {
+                info!("Revoked leftover camera of {}", admin.id)
+            }
+            Ok(_) => warn!("Server rejected revoking leftover camera of {}", admin.id),
+            Err(e) => error!("Failed to revoke leftover camera of {}: {}", admin.id, e),
+        }
     }
 }
 
+/// Select up to `missing` players of each team, preferring players not in `avoid`.
 fn select_random_players(
-    amount: usize,
+    missing: &HashMap<String, usize>,
     players_with_teams: Vec<(Player, String)>,
+    avoid: &HashSet<PlayerId>,
 ) -> Vec<(Player, String)> {
     let mut teams = HashMap::new();
 
@@ -78,7 +482,14 @@ fn select_random_players(
     let mut rng = thread_rng();
 
     for (team, players) in teams {
-        let selected = players.iter().choose_multiple(&mut rng, amount);
+        let amount = missing.get(&team).copied().unwrap_or(0);
+        let (fresh, avoided): (Vec<_>, Vec<_>) = players
+            .iter()
+            .partition(|player| !avoid.contains(&player.id));
+
+        let mut selected = fresh.into_iter().choose_multiple(&mut rng, amount);
+        let remaining = amount - selected.len();
+        selected.extend(avoided.into_iter().choose_multiple(&mut rng, remaining));
 
         for player in selected {
             selected_players.push((player.clone(), team.clone()));
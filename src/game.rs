@@ -0,0 +1,165 @@
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+/// The state of the current match as reported by `GetGameState`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameState {
+    pub allied_score: u64,
+    pub axis_score: u64,
+    pub remaining: Duration,
+    pub map: String,
+}
+
+impl GameState {
+    /// The score of the team which is ahead.
+    pub fn leading_score(&self) -> u64 {
+        self.allied_score.max(self.axis_score)
+    }
+}
+
+impl FromStr for GameState {
+    type Err = String;
+
+    /// Parse the response of `GetGameState`, which looks like the following.
+    /// ```text
+    /// Players: Allied: 45 - Axis: 47
+    /// Score: Allied: 2 - Axis: 3
+    /// Remaining Time: 0:53:42
+    /// Map: foy_warfare
+    /// Next Map: stmariedumont_warfare
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut score = None;
+        let mut remaining = None;
+        let mut map = None;
+
+        for line in s.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+
+            match key.trim() {
+                "Score" => score = Some(parse_teams(value)?),
+                "Remaining Time" => remaining = Some(parse_clock(value)?),
+                "Map" => map = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+
+        let (allied_score, axis_score) = score.ok_or("Missing score")?;
+        Ok(Self {
+            allied_score,
+            axis_score,
+            remaining: remaining.ok_or("Missing remaining time")?,
+            map: map.ok_or("Missing map")?,
+        })
+    }
+}
+
+/// Parse " Allied: 2 - Axis: 3" into both numbers.
+fn parse_teams(value: &str) -> Result<(u64, u64), String> {
+    let (allied, axis) = value
+        .split_once('-')
+        .ok_or_else(|| format!("Malformed teams \"{}\"", value))?;
+
+    let number = |side: &str| {
+        side.rsplit(':')
+            .next()
+            .and_then(|number| number.trim().parse().ok())
+            .ok_or_else(|| format!("Malformed team \"{}\"", side))
+    };
+
+    Ok((number(allied)?, number(axis)?))
+}
+
+/// Parse " 0:53:42" into a duration.
+fn parse_clock(value: &str) -> Result<Duration, String> {
+    let mut seconds = 0;
+    for part in value.trim().split(':') {
+        let part: u64 = part
+            .parse()
+            .map_err(|_| format!("Malformed time \"{}\"", value))?;
+        seconds = seconds * 60 + part;
+    }
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// The last known [`GameState`] and when it was measured, so the match clock can be followed
+/// between measurements.
+#[derive(Debug, Clone)]
+pub struct MatchClock {
+    pub state: GameState,
+    measured: Instant,
+}
+
+impl MatchClock {
+    pub fn new(state: GameState) -> Self {
+        Self {
+            state,
+            measured: Instant::now(),
+        }
+    }
+
+    /// The time left in the match at `now`.
+    pub fn remaining(&self, now: Instant) -> Duration {
+        self.state
+            .remaining
+            .saturating_sub(now.saturating_duration_since(self.measured))
+    }
+
+    /// When exactly `remaining` is left in the match, if that is still ahead.
+    pub fn at_remaining(&self, remaining: Duration) -> Option<Instant> {
+        let until = self.state.remaining.checked_sub(remaining)?;
+        let at = self.measured + until;
+        (at > Instant::now()).then_some(at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_game_state() {
+        let raw = "Players: Allied: 45 - Axis: 47\nScore: Allied: 2 - Axis: 3\nRemaining Time: 0:53:42\nMap: foy_warfare\nNext Map: stmariedumont_warfare";
+
+        assert_eq!(
+            raw.parse(),
+            Ok(GameState {
+                allied_score: 2,
+                axis_score: 3,
+                remaining: Duration::from_secs(53 * 60 + 42),
+                map: "foy_warfare".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_incomplete_game_state() {
+        let raw = "Players: Allied: 45 - Axis: 47\nMap: foy_warfare";
+
+        assert!(raw.parse::<GameState>().is_err());
+        assert!("Score: Allied: two - Axis: 3".parse::<GameState>().is_err());
+    }
+
+    #[test]
+    fn clock_follows_time_between_measurements() {
+        let clock = MatchClock::new(GameState {
+            allied_score: 2,
+            axis_score: 3,
+            remaining: Duration::from_secs(10 * 60),
+            map: "foy_warfare".to_string(),
+        });
+
+        let later = clock.measured + Duration::from_secs(4 * 60);
+        assert_eq!(clock.remaining(later), Duration::from_secs(6 * 60));
+        assert_eq!(
+            clock.at_remaining(Duration::from_secs(5 * 60)),
+            Some(clock.measured + Duration::from_secs(5 * 60))
+        );
+        assert_eq!(clock.at_remaining(Duration::from_secs(20 * 60)), None);
+    }
+}
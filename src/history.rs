@@ -0,0 +1,370 @@
+use std::{collections::HashMap, fs::OpenOptions, io, path::PathBuf, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use tokio::{fs::File, io::AsyncWriteExt, sync::Mutex};
+use uuid::Uuid;
+use wise_api::rcon::parsing::Player;
+
+use crate::{
+    events::EventStatus,
+    manage::command::StartEvent,
+    penalties::{KillKind, PenaltyAction},
+};
+
+/// Configures where the history of all events is kept.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct HistoryConfig {
+    #[serde(default = "default_path")]
+    pub path: PathBuf,
+}
+
+fn default_path() -> PathBuf {
+    PathBuf::from("hellvents_history.jsonl")
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            path: default_path(),
+        }
+    }
+}
+
+/// A single line of the history file. Every record belongs to the event run it names.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "kebab-case")]
+pub enum HistoryRecord {
+    Started {
+        run: String,
+        at: DateTime<Utc>,
+        name: String,
+        config: StartEvent,
+    },
+    Penalty {
+        run: String,
+        at: DateTime<Utc>,
+        killer: Player,
+        victim: Player,
+        weapon: String,
+        kind: KillKind,
+        action: PenaltyAction,
+        infraction: u32,
+    },
+    Ended {
+        run: String,
+        at: DateTime<Utc>,
+        /// The players on the server when the event ended.
+        participants: Vec<Player>,
+        counters: Vec<(String, u64)>,
+    },
+}
+
+impl HistoryRecord {
+    fn run(&self) -> &str {
+        match self {
+            HistoryRecord::Started { run, .. }
+            | HistoryRecord::Penalty { run, .. }
+            | HistoryRecord::Ended { run, .. } => run,
+        }
+    }
+}
+
+/// Everything recorded about a single run of an event.
+#[derive(Debug, Clone)]
+pub struct EventRun {
+    pub name: String,
+    pub config: StartEvent,
+    pub started: DateTime<Utc>,
+    /// Not set if hellvents stopped before the event ended.
+    pub ended: Option<DateTime<Utc>>,
+    pub participants: Vec<Player>,
+    pub penalties: Vec<HistoryRecord>,
+}
+
+/// An append only store of all events which have been run.
+#[derive(Clone)]
+pub struct History {
+    path: PathBuf,
+
+    /// Not set if the file could not be opened, nothing is recorded in that case.
+    file: Option<Arc<Mutex<File>>>,
+}
+
+impl History {
+    pub fn open(config: &HistoryConfig) -> Self {
+        let file = match OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.path)
+        {
+            Ok(file) => Some(Arc::new(Mutex::new(File::from_std(file)))),
+            Err(e) => {
+                error!(
+                    "Failed to open history file {} -> Not recording: {}",
+                    config.path.display(),
+                    e
+                );
+                None
+            }
+        };
+
+        Self {
+            path: config.path.clone(),
+            file,
+        }
+    }
+
+    /// Get a recorder for a new run of an event.
+    pub fn recorder(&self) -> EventRecorder {
+        EventRecorder {
+            history: self.clone(),
+            run: Uuid::new_v4().to_string(),
+        }
+    }
+
+    /// Get a recorder continuing a run which was interrupted by a restart.
+    pub fn resume(&self, run: String) -> EventRecorder {
+        EventRecorder {
+            history: self.clone(),
+            run,
+        }
+    }
+
+    async fn append(&self, record: &HistoryRecord) -> io::Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+
+        let mut guard = file.lock().await;
+        guard.write_all(line.as_bytes()).await?;
+        guard.flush().await
+    }
+
+    /// Read all runs from the history, oldest first.
+    pub async fn runs(&self) -> io::Result<Vec<EventRun>> {
+        let content = match tokio::fs::read_to_string(&self.path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut runs: Vec<EventRun> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let record: HistoryRecord = match serde_json::from_str(line) {
+                Ok(record) => record,
+                Err(e) => {
+                    warn!("Skipping malformed history line {}: {}", number + 1, e);
+                    continue;
+                }
+            };
+
+            if let HistoryRecord::Started {
+                run,
+                at,
+                name,
+                config,
+            } = record
+            {
+                index.insert(run, runs.len());
+                runs.push(EventRun {
+                    name,
+                    config,
+                    started: at,
+                    ended: None,
+                    participants: Vec::new(),
+                    penalties: Vec::new(),
+                });
+                continue;
+            }
+
+            let Some(position) = index.get(record.run()) else {
+                warn!("Skipping history line {} of an unknown run", number + 1);
+                continue;
+            };
+
+            let event_run = &mut runs[*position];
+            match record {
+                HistoryRecord::Ended {
+                    at, participants, ..
+                } => {
+                    event_run.ended = Some(at);
+                    event_run.participants = participants;
+                }
+                penalty => event_run.penalties.push(penalty),
+            }
+        }
+
+        Ok(runs)
+    }
+}
+
+/// Records everything happening during a single run of an event.
+#[derive(Clone)]
+pub struct EventRecorder {
+    history: History,
+    run: String,
+}
+
+impl EventRecorder {
+    pub fn run(&self) -> &str {
+        &self.run
+    }
+
+    async fn record(&self, record: HistoryRecord) {
+        if let Err(e) = self.history.append(&record).await {
+            warn!("Failed to record {:?}: {}", record, e);
+        }
+    }
+
+    pub async fn started(&self, name: &str, config: &StartEvent) {
+        self.record(HistoryRecord::Started {
+            run: self.run.clone(),
+            at: Utc::now(),
+            name: name.to_string(),
+            config: config.clone(),
+        })
+        .await;
+    }
+
+    pub async fn penalty(
+        &self,
+        killer: &Player,
+        victim: &Player,
+        weapon: &str,
+        kind: KillKind,
+        action: &PenaltyAction,
+        infraction: u32,
+    ) {
+        self.record(HistoryRecord::Penalty {
+            run: self.run.clone(),
+            at: Utc::now(),
+            killer: killer.clone(),
+            victim: victim.clone(),
+            weapon: weapon.to_string(),
+            kind,
+            action: action.clone(),
+            infraction,
+        })
+        .await;
+    }
+
+    pub async fn ended(&self, participants: Vec<Player>, status: &EventStatus) {
+        let counters = status
+            .counters
+            .iter()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect();
+
+        self.record(HistoryRecord::Ended {
+            run: self.run.clone(),
+            at: Utc::now(),
+            participants,
+            counters,
+        })
+        .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventPhase;
+
+    fn history() -> (History, PathBuf) {
+        let path = std::env::temp_dir().join(format!("hellvents_{}.jsonl", Uuid::new_v4()));
+        let history = History::open(&HistoryConfig { path: path.clone() });
+        (history, path)
+    }
+
+    fn status() -> EventStatus {
+        EventStatus::new(
+            "Radio Spies",
+            EventPhase::Ended,
+            None,
+            vec![("Intercepted", 3)],
+        )
+    }
+
+    #[tokio::test]
+    async fn appended_runs_are_read_back() {
+        let (history, path) = history();
+        let recorder = history.recorder();
+
+        recorder
+            .started("Radio Spies", &StartEvent::RadioSpies {})
+            .await;
+        recorder.ended(Vec::new(), &status()).await;
+
+        let runs = history.runs().await.unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].name, "Radio Spies");
+        assert!(runs[0].ended.is_some());
+        assert!(runs[0].penalties.is_empty());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn runs_are_oldest_first() {
+        let (history, path) = history();
+        let first = history.recorder();
+        let second = history.recorder();
+
+        first.started("First", &StartEvent::RadioSpies {}).await;
+        second.started("Second", &StartEvent::RadioSpies {}).await;
+        first.ended(Vec::new(), &status()).await;
+
+        let runs = history.runs().await.unwrap();
+        let names: Vec<&str> = runs.iter().map(|run| run.name.as_str()).collect();
+        assert_eq!(names, ["First", "Second"]);
+        assert!(runs[0].ended.is_some());
+        assert!(runs[1].ended.is_none());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn malformed_lines_are_skipped() {
+        let (history, path) = history();
+        let recorder = history.recorder();
+
+        recorder
+            .started("Radio Spies", &StartEvent::RadioSpies {})
+            .await;
+        history
+            .file
+            .as_ref()
+            .unwrap()
+            .lock()
+            .await
+            .write_all(b"{\"record\": \"started\", \"run\n")
+            .await
+            .unwrap();
+        recorder.ended(Vec::new(), &status()).await;
+
+        let runs = history.runs().await.unwrap();
+        assert_eq!(runs.len(), 1);
+        assert!(runs[0].ended.is_some());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn missing_file_has_no_runs() {
+        let path = std::env::temp_dir().join(format!("hellvents_{}.jsonl", Uuid::new_v4()));
+        let history = History { path, file: None };
+
+        assert!(history.runs().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,13 @@
+pub mod checkpoint;
+pub mod client;
+pub mod config;
+pub mod events;
+pub mod game;
+pub mod history;
+pub mod manage;
+pub mod messages;
+pub mod penalties;
+pub mod utils;
+pub mod weapons;
+
+pub use config::{parse_config, FileConfig};
@@ -1,14 +1,5 @@
-pub mod client;
-pub mod config;
-pub mod events;
-pub mod manage;
-pub mod messages;
-pub mod utils;
-
-use client::WsTransceiver;
-pub use config::{parse_config, FileConfig};
+use hellvents::{client::WsTransceiver, manage::listener::CommandListener, parse_config};
 use log::debug;
-use manage::listener::CommandListener;
 use std::error::Error;
 
 #[tokio::main]
@@ -21,11 +12,11 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .install_default()
         .expect("Failed to install default crypto provider");
 
-    let config = parse_config()?;
+    let (config_path, config) = parse_config()?;
     debug!("Initialized file config");
     let transceiver = WsTransceiver::connect(&config.wise).await?;
     debug!("Succesfully connected to wise");
 
-    let mut handler = CommandListener::new(config, transceiver);
+    let mut handler = CommandListener::new(config_path, config, transceiver).await;
     handler.run().await
 }
@@ -1,9 +1,16 @@
 use std::time::Duration;
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+
+use chrono::NaiveTime;
+
+use super::scheduler::{parse_days, parse_time, parse_trigger, MatchTrigger, ScheduleDays};
+use crate::weapons::WeaponCategory;
 
 #[derive(Parser)]
-#[command(version, about)]
+// Help is answered by the listener, clap renders it for terminals.
+#[command(version, about, disable_help_subcommand = true)]
 pub struct HellventCommand {
     #[command(subcommand)]
     pub sub_command: ChatSubcommand,
@@ -18,17 +25,115 @@ pub enum ChatSubcommand {
         event: StartEvent,
     },
 
-    /// End the current event.
+    /// End a running event, all of them if no id is given.
     #[clap(aliases = ["e"])]
-    End,
+    End {
+        /// The id of the event as shown by status.
+        id: Option<u32>,
+    },
+
+    /// Show the status of the running events.
+    #[clap(aliases = ["st"])]
+    Status {
+        /// The page to show.
+        #[clap(default_value = "1")]
+        page: usize,
+    },
+
+    /// List all events which can be started.
+    #[clap(aliases = ["l", "ls"])]
+    List {
+        /// The page to show.
+        #[clap(default_value = "1")]
+        page: usize,
+    },
+
+    /// Reset the infractions of a player during the running events.
+    #[clap(aliases = ["f"])]
+    Forgive {
+        /// The name or id of the player.
+        #[clap(required = true, num_args = 1..)]
+        player: Vec<String>,
+    },
+
+    /// Show the most recent events.
+    #[clap(aliases = ["h"])]
+    History {
+        /// How many events to show.
+        #[clap(default_value = "5", value_parser = parse_count)]
+        count: usize,
 
-    /// Immediately stop the hellvents application.
+        /// The page to show.
+        #[clap(default_value = "1")]
+        page: usize,
+    },
+
+    /// Manage events which are started automatically.
+    #[clap(aliases = ["sch"])]
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleAction,
+    },
+
+    /// Read the config file again and apply what changed.
+    #[clap(aliases = ["r"])]
+    Reload,
+
+    /// Show how to use a command, for example "help start mm" or "help 2" for the second page.
+    #[clap(aliases = ["?"])]
+    Help {
+        /// The command to explain, optionally followed by the page.
+        topic: Vec<String>,
+    },
+
+    /// Immediately stop the hellvents application, running events are not resumed on restart.
     #[clap(aliases = ["eexit"])]
     EmergencyExit,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+#[derive(Subcommand)]
+pub enum ScheduleAction {
+    /// Show all scheduled events.
+    #[clap(aliases = ["l", "ls"])]
+    List {
+        /// The page to show.
+        #[clap(default_value = "1")]
+        page: usize,
+    },
+
+    /// Schedule an event, for example "add sat 20:00 mm 2m 5m".
+    #[clap(aliases = ["a"])]
+    Add {
+        /// Either daily, a list of weekdays such as sat,sun or a date such as 2026-10-24.
+        #[clap(value_parser = parse_days)]
+        days: ScheduleDays,
+
+        /// The time of day in UTC, such as 20:00.
+        #[clap(value_parser = parse_time)]
+        time: NaiveTime,
+
+        #[command(subcommand)]
+        event: StartEvent,
+    },
+
+    /// Start an event at a point in every match, for example "on remaining:5m mm 0s 5m".
+    On {
+        /// Either match-start, remaining:<time> such as remaining:5m or score:<sectors>.
+        #[clap(value_parser = parse_trigger)]
+        trigger: MatchTrigger,
+
+        #[command(subcommand)]
+        event: StartEvent,
+    },
+
+    /// Remove a scheduled event by its id.
+    #[clap(aliases = ["rm"])]
+    Remove { id: u32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand, Serialize, Deserialize)]
 pub enum StartEvent {
+    /// Only melee kills are allowed, everything else is penalized.
     #[clap(aliases = ["mm"])]
     MeleeMania {
         /// The amount of time the starting of the event should be delayed from the annoucement.
@@ -40,14 +145,235 @@ pub enum StartEvent {
         duration: Duration,
     },
 
-    #[clap(skip)]
-    // #[clap(aliases = ["se"])]
+    /// Only the allowed weapon categories may be used or the denied ones may not.
+    #[clap(aliases = ["ra"])]
+    RestrictedArsenal {
+        /// The amount of time the starting of the event should be delayed from the annoucement.
+        #[clap(default_value = "2m", value_parser = humantime::parse_duration)]
+        delay: Duration,
+
+        /// The time for which the mini game should last.
+        #[clap(default_value = "5m", value_parser = humantime::parse_duration)]
+        duration: Duration,
+
+        /// Weapon categories which may be used, kills with any other weapon are penalized.
+        #[clap(
+            long,
+            short,
+            value_enum,
+            value_delimiter = ',',
+            required_unless_present = "deny",
+            conflicts_with = "deny"
+        )]
+        allow: Vec<WeaponCategory>,
+
+        /// Weapon categories which are penalized, all other weapons may be used.
+        #[clap(long, short, value_enum, value_delimiter = ',')]
+        deny: Vec<WeaponCategory>,
+    },
+
+    /// Random players of each team are given admin cam access.
+    #[clap(aliases = ["se"])]
     SkyEye {
         /// How many players are supposed to be given admin cam access per team.
-        #[clap(default_value = "5")]
+        #[clap(default_value = "5", value_parser = parse_count)]
         cam_count: usize,
+
+        /// How often the admin cam access is handed to other players.
+        #[clap(default_value = "5m", value_parser = parse_rotation)]
+        rotation: Duration,
     },
 
+    /// Chat messages are intercepted and relayed to the enemy team.
     #[clap(aliases = ["rs"])]
     RadioSpies {},
 }
+
+impl StartEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StartEvent::MeleeMania { .. } => "Melee Mania",
+            StartEvent::RestrictedArsenal { .. } => "Restricted Arsenal",
+            StartEvent::SkyEye { .. } => "Sky Eye",
+            StartEvent::RadioSpies {} => "Radio Spies",
+        }
+    }
+}
+
+/// Parse an event the way the start command does, such as "mm 2m 5m".
+pub fn parse_start_event(args: &str) -> Result<StartEvent, clap::Error> {
+    let args = ["hellvents", "start"]
+        .into_iter()
+        .chain(args.split_whitespace());
+
+    match HellventCommand::try_parse_from(args)?.sub_command {
+        ChatSubcommand::Start { event } => Ok(event),
+        _ => unreachable!("Start arguments parsed into another subcommand"),
+    }
+}
+
+fn parse_count(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(0) => Err("the count has to be at least 1".to_string()),
+        Ok(count) => Ok(count),
+        Err(_) => Err(format!("\"{}\" is not a number", s)),
+    }
+}
+
+fn parse_rotation(s: &str) -> Result<Duration, String> {
+    match humantime::parse_duration(s) {
+        Ok(rotation) if rotation.is_zero() => Err("the rotation cannot be 0".to_string()),
+        Ok(rotation) => Ok(rotation),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// An event which can be started through [`ChatSubcommand::Start`].
+#[derive(Debug, Clone)]
+pub struct EventDescription {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub about: String,
+}
+
+/// Describe every event which can currently be started.
+pub fn available_events() -> Vec<EventDescription> {
+    let command = HellventCommand::command();
+    let Some(start) = command.find_subcommand("start") else {
+        return Vec::new();
+    };
+
+    start
+        .get_subcommands()
+        .filter(|sub| !sub.is_hide_set() && sub.get_name() != "help")
+        .map(|sub| EventDescription {
+            name: sub.get_name().to_string(),
+            aliases: sub.get_all_aliases().map(|a| a.to_string()).collect(),
+            about: sub.get_about().map(|a| a.to_string()).unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Help for a single command, derived from its clap definition.
+#[derive(Debug, Clone)]
+pub struct CommandHelp {
+    /// How the command is written in chat, such as "/hv start melee-mania [delay] [duration]".
+    pub usage: String,
+    pub about: String,
+
+    /// A line for every argument and subcommand of the command.
+    pub entries: Vec<String>,
+}
+
+/// Describe the command reached by following `topic` through the subcommands by their names or
+/// aliases. An empty topic describes hellvents itself.
+pub fn command_help(topic: &[String]) -> Option<CommandHelp> {
+    let root = HellventCommand::command();
+    let mut command = &root;
+    let mut usage = String::from("/hv");
+    for name in topic {
+        command = command.find_subcommand(name)?;
+        usage.push_str(&format!(" {}", command.get_name()));
+    }
+
+    let mut entries = Vec::new();
+    for arg in command.get_arguments().filter(|arg| !arg.is_hide_set()) {
+        let name = arg.get_id().as_str().replace('_', "-");
+        let mut entry = if arg.is_positional() {
+            if arg.is_required_set() {
+                usage.push_str(&format!(" <{}>", name));
+            } else {
+                usage.push_str(&format!(" [{}]", name));
+            }
+            name
+        } else {
+            match arg.get_short() {
+                Some(short) => format!("--{}/-{}", name, short),
+                None => format!("--{}", name),
+            }
+        };
+
+        let defaults: Vec<_> = arg
+            .get_default_values()
+            .iter()
+            .map(|value| value.to_string_lossy())
+            .collect();
+        if !defaults.is_empty() {
+            entry.push_str(&format!(" = {}", defaults.join(",")));
+        }
+
+        if let Some(help) = arg.get_help() {
+            entry.push_str(&format!(": {}", help));
+        }
+
+        let values: Vec<_> = arg
+            .get_possible_values()
+            .into_iter()
+            .filter(|value| !value.is_hide_set())
+            .map(|value| value.get_name().to_string())
+            .collect();
+        if !values.is_empty() {
+            entry.push_str(&format!(" ({})", values.join(", ")));
+        }
+
+        entries.push(entry);
+    }
+
+    if command.has_subcommands() {
+        usage.push_str(" <command>");
+    }
+    for sub in command.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        let mut entry = sub.get_name().to_string();
+        let aliases: Vec<&str> = sub.get_all_aliases().collect();
+        if !aliases.is_empty() {
+            entry.push_str(&format!(" ({})", aliases.join(", ")));
+        }
+        if let Some(about) = sub.get_about() {
+            entry.push_str(&format!(": {}", about));
+        }
+
+        entries.push(entry);
+    }
+
+    Some(CommandHelp {
+        usage,
+        about: command
+            .get_about()
+            .map(|about| about.to_string())
+            .unwrap_or_default(),
+        entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sky_eye() {
+        let event = parse_start_event("se 3 10m").unwrap();
+
+        let StartEvent::SkyEye {
+            cam_count,
+            rotation,
+        } = event
+        else {
+            panic!("Parsed {:?}", event);
+        };
+        assert_eq!(cam_count, 3);
+        assert_eq!(rotation, Duration::from_secs(600));
+    }
+
+    #[test]
+    fn rejects_zero_sky_eye_values() {
+        assert!(parse_start_event("se 0").is_err());
+        assert!(parse_start_event("se 3 0s").is_err());
+    }
+
+    #[test]
+    fn rejects_zero_history_count() {
+        let command = HellventCommand::try_parse_from(["hellvents", "history", "0"]);
+
+        assert!(command.is_err());
+    }
+}
@@ -0,0 +1,310 @@
+use std::net::SocketAddr;
+
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, oneshot},
+};
+
+use super::permissions::OWNER_ROLE;
+
+/// How many commands from the control interface can wait for the listener.
+const COMMAND_CAPACITY: usize = 16;
+
+/// Configures the local control interface, which accepts the same commands as the chat.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ControlConfig {
+    /// Where the interface listens. It should only ever be bound to localhost, requests and
+    /// their token travel unencrypted.
+    #[serde(default = "default_address")]
+    pub address: SocketAddr,
+
+    /// Every request has to carry this token. The interface is disabled without one.
+    pub token: Option<String>,
+
+    /// The role whose permissions commands from the control interface have.
+    #[serde(default = "default_role")]
+    pub role: String,
+}
+
+fn default_address() -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], 7780))
+}
+
+fn default_role() -> String {
+    OWNER_ROLE.to_string()
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self {
+            address: default_address(),
+            token: None,
+            role: default_role(),
+        }
+    }
+}
+
+/// A single line sent to the control interface.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlRequest {
+    pub token: String,
+
+    /// The command as it would follow `/hv` in chat, split into its arguments.
+    pub args: Vec<String>,
+}
+
+/// The answer to a [`ControlRequest`], sent back as a single line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlResponse {
+    /// Whether the command was accepted and executed.
+    pub ok: bool,
+
+    /// Everything hellvents would have replied in chat.
+    pub replies: Vec<String>,
+}
+
+impl ControlResponse {
+    pub fn rejected(reason: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            replies: vec![reason.into()],
+        }
+    }
+}
+
+/// An authenticated command waiting to be executed by the listener.
+pub struct ControlCommand {
+    pub args: Vec<String>,
+    respond: oneshot::Sender<(ControlResponse, oneshot::Sender<()>)>,
+}
+
+impl ControlCommand {
+    /// Answer the command. The returned receiver completes once the response has been written
+    /// to the connection and fails if it never will be.
+    pub fn respond(self, response: ControlResponse) -> oneshot::Receiver<()> {
+        let (written_tx, written_rx) = oneshot::channel();
+        let _ = self.respond.send((response, written_tx));
+        written_rx
+    }
+}
+
+/// Start serving the control interface if it is configured. Commands arrive on the returned
+/// receiver.
+pub async fn serve(config: &ControlConfig) -> Option<mpsc::Receiver<ControlCommand>> {
+    let token = config.token.clone()?;
+
+    let listener = match TcpListener::bind(config.address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!(
+                "Failed to bind control interface to {} -> Disabling: {}",
+                config.address, e
+            );
+            return None;
+        }
+    };
+
+    if !config.address.ip().is_loopback() {
+        warn!(
+            "Control interface is reachable beyond localhost on {}, its requests are not encrypted",
+            config.address
+        );
+    }
+
+    info!("Serving control interface on {}", config.address);
+    let (sender, receiver) = mpsc::channel(COMMAND_CAPACITY);
+    tokio::spawn(async move {
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Failed to accept control connection: {}", e);
+                    continue;
+                }
+            };
+
+            debug!("Accepted control connection from {}", peer);
+            tokio::spawn(handle_connection(stream, token.clone(), sender.clone()));
+        }
+    });
+
+    Some(receiver)
+}
+
+/// Answer requests on a connection until it is closed, one line per request and response.
+async fn handle_connection(
+    stream: TcpStream,
+    token: String,
+    commands: mpsc::Sender<ControlCommand>,
+) {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Failed to read from control connection: {}", e);
+                break;
+            }
+        };
+
+        let (response, written) = match serde_json::from_str::<ControlRequest>(&line) {
+            Ok(request) if token_matches(&request.token, &token) => {
+                execute(request.args, &commands).await
+            }
+            Ok(_) => {
+                warn!("Rejected control request with an invalid token");
+                (ControlResponse::rejected("Invalid token"), None)
+            }
+            Err(e) => (
+                ControlResponse::rejected(format!("Malformed request: {}", e)),
+                None,
+            ),
+        };
+
+        let mut content = match serde_json::to_string(&response) {
+            Ok(content) => content,
+            Err(e) => {
+                error!("Failed to serialize control response: {}", e);
+                break;
+            }
+        };
+        content.push('\n');
+
+        if let Err(e) = write.write_all(content.as_bytes()).await {
+            warn!("Failed to write to control connection: {}", e);
+            break;
+        }
+
+        if let Some(written) = written {
+            let _ = written.send(());
+        }
+    }
+}
+
+/// Compare the tokens in constant time, so how long the comparison takes does not reveal how
+/// much of a guessed token is right.
+fn token_matches(given: &str, token: &str) -> bool {
+    let (given, token) = (given.as_bytes(), token.as_bytes());
+    if given.len() != token.len() {
+        return false;
+    }
+
+    given
+        .iter()
+        .zip(token)
+        .fold(0, |difference, (a, b)| difference | (a ^ b))
+        == 0
+}
+
+/// Hand the command to the listener and wait for its response, along with the sender to
+/// confirm the response has been written.
+async fn execute(
+    args: Vec<String>,
+    commands: &mpsc::Sender<ControlCommand>,
+) -> (ControlResponse, Option<oneshot::Sender<()>>) {
+    let (respond, response) = oneshot::channel();
+    if commands
+        .send(ControlCommand { args, respond })
+        .await
+        .is_err()
+    {
+        return (
+            ControlResponse::rejected("Hellvents is shutting down"),
+            None,
+        );
+    }
+
+    match response.await {
+        Ok((response, written)) => (response, Some(written)),
+        Err(_) => (
+            ControlResponse::rejected("Hellvents dropped the command"),
+            None,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serve a single connection with the token "secret" and connect to it.
+    async fn connect() -> (BufReader<TcpStream>, mpsc::Receiver<ControlCommand>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (sender, receiver) = mpsc::channel(COMMAND_CAPACITY);
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handle_connection(stream, "secret".to_string(), sender).await;
+        });
+
+        let stream = TcpStream::connect(address).await.unwrap();
+        (BufReader::new(stream), receiver)
+    }
+
+    async fn request(stream: &mut BufReader<TcpStream>, line: &str) -> ControlResponse {
+        stream
+            .get_mut()
+            .write_all(format!("{}\n", line).as_bytes())
+            .await
+            .unwrap();
+
+        let mut response = String::new();
+        stream.read_line(&mut response).await.unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn compares_tokens() {
+        assert!(token_matches("secret", "secret"));
+        assert!(!token_matches("secreT", "secret"));
+        assert!(!token_matches("secret!", "secret"));
+        assert!(!token_matches("", "secret"));
+    }
+
+    #[tokio::test]
+    async fn executes_authenticated_requests() {
+        let (mut stream, mut commands) = connect().await;
+        tokio::spawn(async move {
+            let command = commands.recv().await.unwrap();
+            assert_eq!(command.args, ["status"]);
+            command.respond(ControlResponse {
+                ok: true,
+                replies: vec!["HELLVENTS | STATUS".to_string()],
+            });
+        });
+
+        let response = request(&mut stream, r#"{"token": "secret", "args": ["status"]}"#).await;
+
+        assert!(response.ok);
+        assert_eq!(response.replies, ["HELLVENTS | STATUS"]);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_tokens() {
+        let (mut stream, mut commands) = connect().await;
+
+        let response = request(&mut stream, r#"{"token": "guess", "args": ["status"]}"#).await;
+
+        assert!(!response.ok);
+        assert_eq!(response.replies, ["Invalid token"]);
+        assert!(commands.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_lines_and_keeps_going() {
+        let (mut stream, _commands) = connect().await;
+
+        let response = request(&mut stream, "status").await;
+        assert!(!response.ok);
+        assert!(response.replies[0].starts_with("Malformed request"));
+
+        let response = request(&mut stream, r#"{"token": "guess", "args": []}"#).await;
+        assert_eq!(response.replies, ["Invalid token"]);
+    }
+}
@@ -1,49 +1,259 @@
-use std::{error::Error, process};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt::Display,
+    future::pending,
+    iter,
+    path::PathBuf,
+    process,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use clap::Parser;
-use log::{debug, info};
+use chrono::Utc;
+use clap::{error::ErrorKind, Parser};
+use log::{debug, info, warn};
+use tokio::{
+    sync::mpsc,
+    time::{self, sleep_until},
+};
 use wise_api::{
     events::RconEvent,
-    messages::{ClientWsRequest, CommandRequestKind, ServerWsMessage},
+    messages::ServerWsMessage,
     rcon::parsing::{
         showlog::{LogKind, LogLine},
         Player,
     },
 };
 
-use super::command::{HellventCommand, StartEvent};
+use super::{
+    command::{available_events, command_help, HellventCommand, ScheduleAction, StartEvent},
+    control::{self, ControlCommand, ControlResponse},
+    permissions::{Permission, Permissions},
+    scheduler::{RemoveOutcome, Schedule, Scheduler, When},
+};
 use crate::{
-    client::WsTransceiver,
-    config::FileConfig,
-    events::{build_event, Event},
+    checkpoint::{from_wall_clock, Checkpoints},
+    client::{WsTransceiver, WsTransceiverExt},
+    config::{load_config, FileConfig},
+    events::{
+        build_event, resume_event, revoke_orphaned_cams, EventHandle, Forgiveness, MatchEndPolicy,
+    },
+    game::MatchClock,
+    history::History,
     manage::command::ChatSubcommand,
+    messages::admin::*,
+    penalties::PenaltyLadder,
+    weapons::WeaponCatalogue,
 };
 
 const COMMAND_PREFIX: &str = "/";
 
+/// Who issued a command, replies go back the way the command came in.
+enum Issuer {
+    Player(Player),
+    /// A command from the control interface, its replies are collected and sent at once.
+    Control(Vec<String>),
+}
+
+impl Display for Issuer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Issuer::Player(player) => write!(f, "{:?}", player),
+            Issuer::Control(_) => f.write_str("the control interface"),
+        }
+    }
+}
+
+/// How often the game state is checked for match triggers.
+const GAME_STATE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long an event may take to shut down after it has been told to stop.
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+/// How long an emergency exit waits for its response to reach the control interface.
+const EXIT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub struct CommandListener {
+    config_path: PathBuf,
     config: FileConfig,
     transceiver: WsTransceiver,
-    event: Option<Box<dyn Event>>,
+    weapons: Arc<WeaponCatalogue>,
+    penalties: Arc<PenaltyLadder>,
+    history: History,
+    checkpoints: Checkpoints,
+    scheduler: Scheduler,
+    permissions: Permissions,
+
+    /// Commands from the control interface, not set if it is disabled.
+    control: Option<mpsc::Receiver<ControlCommand>>,
+
+    /// The last known state of the match, only followed while there are match triggers.
+    clock: Option<MatchClock>,
+
+    /// The running events by the id admins refer to them with.
+    events: BTreeMap<u32, EventHandle>,
+    next_event_id: u32,
+
+    /// Set by an emergency exit, the process exits as soon as the issuer has been answered.
+    exiting: bool,
 }
 
 impl CommandListener {
-    pub fn new(config: FileConfig, transceiver: WsTransceiver) -> Self {
+    pub async fn new(config_path: PathBuf, config: FileConfig, transceiver: WsTransceiver) -> Self {
         Self {
+            config_path,
+            scheduler: Scheduler::load(&config.scheduler, &config.schedules).await,
+            weapons: Arc::new(WeaponCatalogue::from_config(&config.weapons)),
+            penalties: Arc::new(PenaltyLadder::from_config(&config.penalties)),
+            history: History::open(&config.history),
+            checkpoints: Checkpoints::new(&config.checkpoint),
+            permissions: Permissions::from_config(&config.admin),
+            control: control::serve(&config.control).await,
             config,
             transceiver,
-            event: None,
+            clock: None,
+            events: BTreeMap::new(),
+            next_event_id: 1,
+            exiting: false,
         }
     }
 
     pub async fn run(&mut self) -> Result<(), Box<dyn Error>> {
+        self.recover_events().await;
+
+        let mut game_poll = time::interval(GAME_STATE_INTERVAL);
+        game_poll.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
+
         info!("Listening for commands");
         loop {
-            let message = self.transceiver.receive().await;
-            self.server_message(message).await;
+            let next_due = self.scheduler.next_due().map(from_wall_clock);
+            let next_match_due = self
+                .clock
+                .as_ref()
+                .and_then(|clock| self.scheduler.next_match_due(clock));
+            self.events.retain(|_, event| !event.is_finished());
+
+            tokio::select! {
+                message = self.transceiver.receive() => {
+                    let message = message?;
+                    self.server_message(message).await;
+                }
+                _ = sleep_until_due(next_due) => {
+                    let events = self.scheduler.take_due().await;
+                    self.start_automatic(events).await;
+                }
+                _ = sleep_until_due(next_match_due) => self.check_match_triggers().await,
+                _ = game_poll.tick() => self.update_clock().await,
+                Some(command) = receive_control(&mut self.control) => {
+                    self.control_command(command).await;
+                }
+            }
+        }
+    }
+
+    /// Follow the game state while any schedule is triggered by it.
+    async fn update_clock(&mut self) {
+        if !self.scheduler.has_match_triggers() {
+            self.clock = None;
+            return;
+        }
+
+        match self.transceiver.get_gamestate().await {
+            Ok(state) => {
+                self.clock = Some(MatchClock::new(state));
+                self.check_match_triggers().await;
+            }
+            Err(e) => warn!("Failed to get game state: {}", e),
+        }
+    }
+
+    async fn check_match_triggers(&mut self) {
+        let Some(clock) = &self.clock else {
+            return;
+        };
+
+        let events = self.scheduler.take_match_triggered(clock);
+        self.start_automatic(events).await;
+    }
+
+    /// Start events from schedules or triggers, unless they conflict with a running event.
+    async fn start_automatic(&mut self, events: Vec<StartEvent>) {
+        for event in events {
+            if let Some(running) = self
+                .events
+                .values()
+                .find(|running| !running.is_finished() && running.conflicts_with(&event))
+            {
+                warn!(
+                    "Skipping scheduled {} as it conflicts with the running {}",
+                    event.name(),
+                    running.name()
+                );
+                continue;
+            }
+
+            info!("Starting scheduled {}", event.name());
+            self.start_event(event).await;
+        }
+    }
+
+    /// Stop the running events or pause them until the next match, depending on their policy.
+    async fn match_ended(&mut self) {
+        let ids: Vec<u32> = self.events.keys().copied().collect();
+        for id in ids {
+            let event = &self.events[&id];
+            if event.is_finished() {
+                continue;
+            }
+
+            match self.config.match_end.policy(event.start()) {
+                MatchEndPolicy::Stop => {
+                    info!("Match has ended -> Stopping {}", event.name());
+                    self.stop_event(id).await;
+                }
+                MatchEndPolicy::CarryOver => event.pause().await,
+            }
+        }
+    }
+
+    /// Pick up the events interrupted by a restart, either continuing or cleanly ending them.
+    /// Cameras granted by Sky Eye that no resumed event holds are revoked afterwards.
+    async fn recover_events(&mut self) {
+        let checkpoints = match self.checkpoints.load().await {
+            Ok(checkpoints) => checkpoints,
+            Err(e) => {
+                warn!("Failed to load checkpoints -> Discarding: {}", e);
+                Vec::new()
+            }
+        };
+
+        let mut resumed = Vec::new();
+        for checkpoint in checkpoints {
+            if self.config.checkpoint.resume {
+                resumed.push(checkpoint.state.clone());
+            }
+
+            let event = resume_event(
+                self.transceiver.clone(),
+                self.weapons.clone(),
+                self.penalties.clone(),
+                &self.history,
+                self.checkpoints.clone(),
+                checkpoint,
+            )
+            .await;
+
+            if self.config.checkpoint.resume {
+                info!("Resuming interrupted event {}", event.name());
+                self.insert_event(event);
+            } else {
+                info!("Ending interrupted event {}", event.name());
+                event.stop(STOP_GRACE_PERIOD).await;
+            }
         }
 
-        // Handle wise shutting off... Reattempt every few minutes
+        revoke_orphaned_cams(self.transceiver.clone(), &resumed).await;
     }
 
     async fn server_message(&mut self, message: ServerWsMessage) {
@@ -55,20 +265,38 @@ impl CommandListener {
                 },
                 ..
             })) => self.chat_log(sender, content).await,
-            ServerWsMessage::Authenticated => todo!(),
+            ServerWsMessage::Rcon(RconEvent::Log(LogLine {
+                kind: LogKind::MatchStart { map },
+                ..
+            })) => {
+                info!("Match on {} has started", map);
+                for event in self.events.values() {
+                    event.resume().await;
+                }
+
+                let events = self.scheduler.match_started();
+                self.update_clock().await;
+                self.start_automatic(events).await;
+            }
+            ServerWsMessage::Rcon(RconEvent::Log(LogLine {
+                kind: LogKind::MatchEnded { map, .. },
+                ..
+            })) => {
+                info!("Match on {} has ended", map);
+                self.scheduler.match_ended();
+                self.match_ended().await;
+            }
+            // Sent by the transceiver after it has reconnected to wise.
+            ServerWsMessage::Authenticated => info!("Connection to wise has been reestablished"),
             _ => {}
         }
     }
 
     async fn chat_log(&mut self, sender: Player, content: String) {
-        if !self
-            .config
-            .admin
-            .allowed_ids
-            .contains(&sender.id.to_string())
-        {
+        let Some(role) = self.permissions.role(&sender.id.to_string()) else {
             return;
-        }
+        };
+        let role = role.to_string();
 
         if !content.starts_with(COMMAND_PREFIX) {
             return;
@@ -76,57 +304,460 @@ impl CommandListener {
 
         debug!("Received command from {:?} as \"{}\"", sender, content);
 
+        let mut issuer = Issuer::Player(sender);
+        let args: Vec<String> = content
+            .split_whitespace()
+            .skip(1)
+            .map(String::from)
+            .collect();
         let parsed = parse_command(content);
         let command = match parsed {
             Ok(Some(cmd)) => cmd,
             Ok(None) => return,
             Err(e) => {
-                self.send_error_reply(e, &sender).await;
+                self.reply(&mut issuer, &parse_error_message(&args, &e))
+                    .await;
+                return;
+            }
+        };
+
+        self.execute(&mut issuer, &role, command).await;
+        if self.exiting {
+            process::exit(1);
+        }
+    }
+
+    /// Execute a command from the control interface and answer it with all replies.
+    async fn control_command(&mut self, command: ControlCommand) {
+        debug!(
+            "Received command from the control interface as {:?}",
+            command.args
+        );
+
+        let args = iter::once("hellvents".to_string()).chain(command.args.iter().cloned());
+        let parsed = match HellventCommand::try_parse_from(args) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                let response = ControlResponse::rejected(parse_error_message(&command.args, &e));
+                command.respond(response);
                 return;
             }
         };
 
+        let role = self.config.control.role.clone();
+        let mut issuer = Issuer::Control(Vec::new());
+        let ok = self.execute(&mut issuer, &role, parsed).await;
+
+        let Issuer::Control(replies) = issuer else {
+            unreachable!("Issuer changed while executing");
+        };
+        let written = command.respond(ControlResponse { ok, replies });
+
+        if self.exiting {
+            if time::timeout(EXIT_RESPONSE_TIMEOUT, written).await.is_err() {
+                warn!("Control interface did not confirm the emergency exit in time");
+            }
+            process::exit(1);
+        }
+    }
+
+    /// Execute a command if the role of the issuer permits it, returns whether it did.
+    async fn execute(&mut self, issuer: &mut Issuer, role: &str, command: HellventCommand) -> bool {
+        if let Some(permission) = Permission::required(&command.sub_command) {
+            if !self.permissions.role_permits(role, permission) {
+                info!(
+                    "Denied {} the {} permission as {}",
+                    issuer, permission, role
+                );
+                self.reply(issuer, &permission_denied_message(role, permission))
+                    .await;
+                return false;
+            }
+        }
+
         match command.sub_command {
-            ChatSubcommand::Start { event } => self.start_event(event),
-            ChatSubcommand::End => self.stop_event(),
-            ChatSubcommand::EmergencyExit => process::exit(1),
+            ChatSubcommand::Start { event } => {
+                let (id, stopped) = self.start_event(event.clone()).await;
+                self.reply(issuer, &event_started_message(id, &event, &stopped))
+                    .await;
+            }
+            ChatSubcommand::End { id: Some(id) } => {
+                let message = match self.stop_event(id).await {
+                    Some((name, ran_for)) => events_ended_message(&[(id, name, ran_for)]),
+                    None => event_not_found_message(id),
+                };
+                self.reply(issuer, &message).await;
+            }
+            ChatSubcommand::End { id: None } => {
+                let ended = self.stop_events().await;
+                self.reply(issuer, &events_ended_message(&ended)).await;
+            }
+            ChatSubcommand::Status { page } => self.send_status(issuer, page).await,
+            ChatSubcommand::List { page } => self.send_list(issuer, page).await,
+            ChatSubcommand::Schedule { action } => self.schedule(issuer, action).await,
+            ChatSubcommand::History { count, page } => self.send_history(issuer, count, page).await,
+            ChatSubcommand::Forgive { player } => self.forgive(issuer, &player.join(" ")).await,
+            ChatSubcommand::Reload => self.reload(issuer).await,
+            ChatSubcommand::Help { topic } => self.send_help(issuer, topic).await,
+            ChatSubcommand::EmergencyExit => {
+                // Nothing is cleaned up, so the events must not be resumed after a restart.
+                warn!("Emergency exit requested by {} -> Exiting", issuer);
+                self.checkpoints.discard().await;
+                self.reply(issuer, &emergency_exit_message()).await;
+                self.exiting = true;
+            }
         }
+
+        true
     }
 
-    async fn send_error_reply(&mut self, error: clap::Error, sender: &Player) {
-        let rendered = error.render();
-        let command = format!("Message {} {}", sender.id.to_string(), rendered.to_string());
-        self.transceiver
-            .request(ClientWsRequest::Execute(CommandRequestKind::Raw {
-                command,
-                long_response: false,
-            }))
+    async fn send_status(&mut self, issuer: &mut Issuer, page: usize) {
+        let mut statuses = Vec::new();
+        for (id, event) in &self.events {
+            statuses.push((*id, event.status().await));
+        }
+
+        let message = if statuses.is_empty() {
+            no_event_message()
+        } else {
+            status_message(&statuses, self.transceiver.total_missed_messages(), page)
+        };
+
+        self.reply(issuer, &message).await;
+    }
+
+    /// Read the config file again and swap in everything which can change while running. The
+    /// new file is validated completely before anything is applied.
+    async fn reload(&mut self, issuer: &mut Issuer) {
+        let mut config = match self.validated_config() {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("Failed to reload config -> Keeping the current one: {}", e);
+                self.reply(issuer, &reload_failed_message(&e)).await;
+                return;
+            }
+        };
+
+        let current = &self.config;
+        let mut changed = Vec::new();
+        let mut restart = Vec::new();
+
+        // The connection, the checkpoints and the control interface are set up once.
+        if config.wise != current.wise {
+            restart.push("wise");
+        }
+        if config.checkpoint != current.checkpoint {
+            restart.push("checkpoint");
+        }
+        if config.control.address != current.control.address
+            || config.control.token != current.control.token
+        {
+            restart.push("control");
+        }
+        config.wise = current.wise.clone();
+        config.checkpoint = current.checkpoint.clone();
+        config.control.address = current.control.address;
+        config.control.token = current.control.token.clone();
+
+        if config.admin != current.admin {
+            changed.push("admin");
+        }
+        if config.control.role != current.control.role {
+            changed.push("control.role");
+        }
+        if config.weapons != current.weapons {
+            changed.push("weapons");
+        }
+        if config.penalties != current.penalties {
+            changed.push("penalties");
+        }
+        if config.history != current.history {
+            changed.push("history");
+        }
+        if config.match_end != current.match_end {
+            changed.push("match_end");
+        }
+        let schedules_changed =
+            config.scheduler != current.scheduler || config.schedules != current.schedules;
+        if schedules_changed {
+            changed.push("schedules");
+        }
+
+        // Build everything first, so the swap below happens all at once.
+        let permissions = Permissions::from_config(&config.admin);
+        let weapons = Arc::new(WeaponCatalogue::from_config(&config.weapons));
+        let penalties = Arc::new(PenaltyLadder::from_config(&config.penalties));
+        let history = (config.history != current.history).then(|| History::open(&config.history));
+        let scheduler = if schedules_changed {
+            let mut scheduler = Scheduler::load(&config.scheduler, &config.schedules).await;
+            scheduler.continue_from(&self.scheduler);
+            Some(scheduler)
+        } else {
+            None
+        };
+
+        self.permissions = permissions;
+        self.weapons = weapons;
+        self.penalties = penalties;
+        if let Some(history) = history {
+            self.history = history;
+        }
+        if let Some(scheduler) = scheduler {
+            self.scheduler = scheduler;
+        }
+        self.config = config;
+
+        info!(
+            "Reloaded config, applied {:?}, needs a restart {:?}",
+            changed, restart
+        );
+        self.reply(issuer, &reload_message(&changed, &restart))
             .await;
-        return;
     }
 
-    fn start_event(&mut self, event: StartEvent) {
-        if let Some(current_event) = self.event.take() {
-            info!("Stopping current before starting new");
-            current_event.stop();
+    /// Load the config file and check everything which would otherwise only be warned about.
+    fn validated_config(&self) -> Result<FileConfig, String> {
+        let config = load_config(&self.config_path).map_err(|e| e.to_string())?;
+
+        for schedule in &config.schedules {
+            Schedule::from_config(schedule)
+                .map_err(|e| format!("Invalid schedule \"{}\": {}", schedule.event, e))?;
         }
+        config.weapons.validate()?;
+        config.penalties.validate()?;
 
-        let event = build_event(self.transceiver.clone(), &event);
-        event.start();
-        self.event = Some(event);
+        let permissions = Permissions::from_config(&config.admin);
+        if config.control.token.is_some() && !permissions.has_role(&config.control.role) {
+            return Err(format!(
+                "The control role {} does not exist",
+                config.control.role
+            ));
+        }
+        for (id, role) in &config.admin.members {
+            if !permissions.has_role(role) {
+                return Err(format!("Player {} has the unknown role {}", id, role));
+            }
+        }
+
+        Ok(config)
     }
 
-    fn stop_event(&mut self) {
-        let Some(event) = self.event.take() else {
+    async fn send_help(&mut self, issuer: &mut Issuer, mut topic: Vec<String>) {
+        let page = match topic.last().and_then(|last| last.parse().ok()) {
+            Some(page) => {
+                topic.pop();
+                page
+            }
+            None => 1,
+        };
+
+        let message = match command_help(&topic) {
+            Some(help) => help_message(&help, &topic, page),
+            None => help_unknown_message(&topic),
+        };
+        self.reply(issuer, &message).await;
+    }
+
+    async fn send_list(&mut self, issuer: &mut Issuer, page: usize) {
+        let message = list_message(&available_events(), page);
+        self.reply(issuer, &message).await;
+    }
+
+    async fn schedule(&mut self, issuer: &mut Issuer, action: ScheduleAction) {
+        let message = match action {
+            ScheduleAction::List { page } => schedule_list_message(self.scheduler.entries(), page),
+            ScheduleAction::Add { days, time, event } => {
+                let schedule = Schedule {
+                    when: When::Time { days, time },
+                    event,
+                };
+                match schedule.next_after(Utc::now()) {
+                    Some(next) => {
+                        let id = self.scheduler.add(schedule.clone()).await;
+                        schedule_added_message(id, &schedule, Some(next))
+                    }
+                    None => schedule_passed_message(&schedule),
+                }
+            }
+            ScheduleAction::On { trigger, event } => {
+                let schedule = Schedule {
+                    when: When::Match { trigger },
+                    event,
+                };
+                let id = self.scheduler.add(schedule.clone()).await;
+                schedule_added_message(id, &schedule, None)
+            }
+            ScheduleAction::Remove { id } => match self.scheduler.remove(id).await {
+                RemoveOutcome::Removed(schedule) => schedule_removed_message(id, &schedule),
+                RemoveOutcome::FromConfig => schedule_from_config_message(id),
+                RemoveOutcome::NotFound => schedule_not_found_message(id),
+            },
+        };
+
+        self.reply(issuer, &message).await;
+    }
+
+    async fn send_history(&mut self, issuer: &mut Issuer, count: usize, page: usize) {
+        let message = match self.history.runs().await {
+            Ok(runs) => {
+                let recent = &runs[runs.len().saturating_sub(count)..];
+                history_message(recent, count, page)
+            }
+            Err(e) => {
+                warn!("Failed to read the history: {}", e);
+                history_unavailable_message()
+            }
+        };
+
+        self.reply(issuer, &message).await;
+    }
+
+    async fn forgive(&mut self, issuer: &mut Issuer, player: &str) {
+        if self.events.is_empty() {
+            self.reply(issuer, &no_event_message()).await;
             return;
+        }
+
+        let mut outcome = Forgiveness::Unsupported;
+        for event in self.events.values() {
+            match event.forgive(player).await {
+                Forgiveness::Forgiven(forgiven) => {
+                    outcome = Forgiveness::Forgiven(forgiven);
+                    break;
+                }
+                Forgiveness::UnknownPlayer => outcome = Forgiveness::UnknownPlayer,
+                Forgiveness::Unsupported => {}
+            }
+        }
+
+        let message = match outcome {
+            Forgiveness::Forgiven(forgiven) => {
+                let notice = forgiven_player_message();
+                if let Err(e) = self.transceiver.message_player(&forgiven.id, &notice).await {
+                    warn!("Failed to inform {:?} about forgiveness: {}", forgiven, e);
+                }
+                forgiven_message(&forgiven.name)
+            }
+            Forgiveness::UnknownPlayer => no_infractions_message(player),
+            Forgiveness::Unsupported => {
+                let names: Vec<&str> = self.events.values().map(|event| event.name()).collect();
+                forgive_unsupported_message(&names.join(", "))
+            }
         };
 
-        event.stop();
+        self.reply(issuer, &message).await;
+    }
+
+    /// Answer the admin who issued a command the way the command came in.
+    async fn reply(&mut self, issuer: &mut Issuer, message: &str) {
+        match issuer {
+            Issuer::Player(sender) => {
+                if let Err(e) = self.transceiver.message_player(&sender.id, message).await {
+                    warn!("Failed to reply to {:?}: {}", sender, e);
+                }
+            }
+            Issuer::Control(replies) => replies.push(message.to_string()),
+        }
+    }
+
+    /// Start an event, stopping the running events it conflicts with first. Returns the id of
+    /// the new event and the ids and names of the stopped ones.
+    async fn start_event(&mut self, event: StartEvent) -> (u32, Vec<(u32, &'static str)>) {
+        let conflicting: Vec<u32> = self
+            .events
+            .iter()
+            .filter(|(_, running)| running.conflicts_with(&event))
+            .map(|(id, _)| *id)
+            .collect();
+
+        let mut stopped = Vec::new();
+        for id in conflicting {
+            info!("Stopping conflicting event #{} before starting new", id);
+            if let Some((name, _)) = self.stop_event(id).await {
+                stopped.push((id, name));
+            }
+        }
+
+        let handle = build_event(
+            self.transceiver.clone(),
+            self.weapons.clone(),
+            self.penalties.clone(),
+            &self.history,
+            self.checkpoints.clone(),
+            &event,
+        );
+        (self.insert_event(handle), stopped)
+    }
+
+    fn insert_event(&mut self, event: EventHandle) -> u32 {
+        let id = self.next_event_id;
+        self.next_event_id += 1;
+        info!("Event {} is running as #{}", event.name(), id);
+        self.events.insert(id, event);
+        id
+    }
+
+    /// Stop an event, returns its name and how long it ran if it was running.
+    async fn stop_event(&mut self, id: u32) -> Option<(&'static str, Duration)> {
+        let event = self.events.remove(&id)?;
+        let ended = (event.name(), event.running_for());
+
+        event.stop(STOP_GRACE_PERIOD).await;
+        Some(ended)
+    }
+
+    /// Stop every running event, returns their ids, names and how long they ran.
+    async fn stop_events(&mut self) -> Vec<(u32, &'static str, Duration)> {
+        let mut ended = Vec::new();
+        while let Some((id, event)) = self.events.pop_first() {
+            ended.push((id, event.name(), event.running_for()));
+            event.stop(STOP_GRACE_PERIOD).await;
+        }
+
+        ended
+    }
+}
+
+/// Answer help requested from clap, such as with "--help" or by leaving out a subcommand, with
+/// the chat help and everything else with a condensed error.
+fn parse_error_message(args: &[String], error: &clap::Error) -> String {
+    if matches!(
+        error.kind(),
+        ErrorKind::DisplayHelp | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand
+    ) {
+        let topic: Vec<String> = args
+            .iter()
+            .filter(|arg| !arg.starts_with('-') && *arg != "help")
+            .cloned()
+            .collect();
+        if let Some(help) = command_help(&topic) {
+            return help_message(&help, &topic, 1);
+        }
+    }
+
+    command_error_message(error)
+}
+
+/// Wait for the next command from the control interface, forever if it is disabled.
+async fn receive_control(
+    control: &mut Option<mpsc::Receiver<ControlCommand>>,
+) -> Option<ControlCommand> {
+    match control {
+        Some(control) => control.recv().await,
+        None => pending().await,
+    }
+}
+
+/// Wait until `due`, forever if there is nothing due.
+async fn sleep_until_due(due: Option<Instant>) {
+    match due {
+        Some(due) => sleep_until(due.into()).await,
+        None => pending().await,
     }
 }
 
 fn parse_command(content: String) -> Result<Option<HellventCommand>, clap::Error> {
-    let mut split = content.split(" ");
+    let mut split = content.split_whitespace();
     let valid_commands = vec!["/hellvents", "/hv"];
     if !valid_commands.contains(&split.nth(0).unwrap_or("")) {
         return Ok(None);
@@ -1,2 +1,5 @@
 pub mod command;
+pub mod control;
 pub mod listener;
+pub mod permissions;
+pub mod scheduler;
@@ -0,0 +1,250 @@
+use std::{collections::HashMap, fmt::Display};
+
+use log::{debug, warn};
+use serde::Deserialize;
+
+use super::command::{ChatSubcommand, ScheduleAction};
+use crate::config::AdminConfig;
+
+/// The role every player in `allowed_ids` has. It holds every permission unless it is
+/// configured otherwise.
+pub const OWNER_ROLE: &str = "owner";
+
+/// What a role allows its members to do, roughly one permission per subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Permission {
+    Start,
+    End,
+    Status,
+    List,
+    Forgive,
+    History,
+    /// Viewing the schedules.
+    Schedule,
+    /// Adding and removing schedules.
+    ManageSchedules,
+    /// Reloading the config file.
+    Reload,
+    EmergencyExit,
+}
+
+impl Permission {
+    pub const ALL: &'static [Permission] = &[
+        Permission::Start,
+        Permission::End,
+        Permission::Status,
+        Permission::List,
+        Permission::Forgive,
+        Permission::History,
+        Permission::Schedule,
+        Permission::ManageSchedules,
+        Permission::Reload,
+        Permission::EmergencyExit,
+    ];
+
+    /// The permission needed to issue the command, every admin may ask for help.
+    pub fn required(command: &ChatSubcommand) -> Option<Self> {
+        let permission = match command {
+            ChatSubcommand::Start { .. } => Permission::Start,
+            ChatSubcommand::End { .. } => Permission::End,
+            ChatSubcommand::Status { .. } => Permission::Status,
+            ChatSubcommand::List { .. } => Permission::List,
+            ChatSubcommand::Forgive { .. } => Permission::Forgive,
+            ChatSubcommand::History { .. } => Permission::History,
+            ChatSubcommand::Schedule {
+                action: ScheduleAction::List { .. },
+            } => Permission::Schedule,
+            ChatSubcommand::Schedule { .. } => Permission::ManageSchedules,
+            ChatSubcommand::Reload => Permission::Reload,
+            ChatSubcommand::Help { .. } => return None,
+            ChatSubcommand::EmergencyExit => Permission::EmergencyExit,
+        };
+
+        Some(permission)
+    }
+}
+
+impl Display for Permission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let permission = match self {
+            Permission::Start => "start",
+            Permission::End => "end",
+            Permission::Status => "status",
+            Permission::List => "list",
+            Permission::Forgive => "forgive",
+            Permission::History => "history",
+            Permission::Schedule => "schedule",
+            Permission::ManageSchedules => "manage-schedules",
+            Permission::Reload => "reload",
+            Permission::EmergencyExit => "emergency-exit",
+        };
+        f.write_str(permission)
+    }
+}
+
+/// Decides which admin may issue which command.
+#[derive(Debug, Clone)]
+pub struct Permissions {
+    roles: HashMap<String, Vec<Permission>>,
+
+    /// The role of every admin by their player id.
+    members: HashMap<String, String>,
+}
+
+impl Permissions {
+    pub fn from_config(config: &AdminConfig) -> Self {
+        let mut roles = config.roles.clone();
+        roles
+            .entry(OWNER_ROLE.to_string())
+            .or_insert_with(|| Permission::ALL.to_vec());
+
+        let mut members = config.members.clone();
+        for id in &config.allowed_ids {
+            if let Some(role) = members.insert(id.clone(), OWNER_ROLE.to_string()) {
+                warn!(
+                    "Player {} is listed in allowed_ids and as {} -> Treating as {}",
+                    id, role, OWNER_ROLE
+                );
+            }
+        }
+
+        for (id, role) in &members {
+            if !roles.contains_key(role) {
+                warn!("Player {} has the unknown role {} -> Ignoring", id, role);
+            }
+        }
+
+        debug!("Loaded {} roles for {} admins", roles.len(), members.len());
+        Self { roles, members }
+    }
+
+    /// The role of the player, if they are an admin at all.
+    pub fn role(&self, id: &str) -> Option<&str> {
+        let role = self.members.get(id)?;
+        self.roles.contains_key(role).then_some(role.as_str())
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.contains_key(role)
+    }
+
+    pub fn role_permits(&self, role: &str, permission: Permission) -> bool {
+        self.roles
+            .get(role)
+            .is_some_and(|permissions| permissions.contains(&permission))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::manage::command::HellventCommand;
+
+    fn config(
+        allowed_ids: &[&str],
+        roles: &[(&str, &[Permission])],
+        members: &[(&str, &str)],
+    ) -> AdminConfig {
+        AdminConfig {
+            allowed_ids: allowed_ids.iter().map(|id| id.to_string()).collect(),
+            roles: roles
+                .iter()
+                .map(|(role, permissions)| (role.to_string(), permissions.to_vec()))
+                .collect(),
+            members: members
+                .iter()
+                .map(|(id, role)| (id.to_string(), role.to_string()))
+                .collect(),
+        }
+    }
+
+    fn required(command: &str) -> Option<Permission> {
+        let args = ["hellvents"].into_iter().chain(command.split_whitespace());
+        let command = HellventCommand::try_parse_from(args).unwrap();
+        Permission::required(&command.sub_command)
+    }
+
+    #[test]
+    fn allowed_ids_are_owners_with_every_permission() {
+        let permissions = Permissions::from_config(&config(&["1"], &[], &[]));
+
+        assert_eq!(permissions.role("1"), Some(OWNER_ROLE));
+        for permission in Permission::ALL {
+            assert!(permissions.role_permits(OWNER_ROLE, *permission));
+        }
+    }
+
+    #[test]
+    fn allowed_ids_override_members() {
+        let permissions = Permissions::from_config(&config(
+            &["1"],
+            &[("moderator", &[Permission::Status])],
+            &[("1", "moderator")],
+        ));
+
+        assert_eq!(permissions.role("1"), Some(OWNER_ROLE));
+    }
+
+    #[test]
+    fn configured_owner_role_is_kept() {
+        let permissions =
+            Permissions::from_config(&config(&["1"], &[(OWNER_ROLE, &[Permission::Status])], &[]));
+
+        assert!(permissions.role_permits(OWNER_ROLE, Permission::Status));
+        assert!(!permissions.role_permits(OWNER_ROLE, Permission::EmergencyExit));
+    }
+
+    #[test]
+    fn unknown_roles_grant_nothing() {
+        let permissions = Permissions::from_config(&config(&[], &[], &[("1", "ghost")]));
+
+        assert_eq!(permissions.role("1"), None);
+        assert_eq!(permissions.role("2"), None);
+        assert!(!permissions.has_role("ghost"));
+        assert!(!permissions.role_permits("ghost", Permission::Status));
+    }
+
+    #[test]
+    fn custom_roles_grant_only_their_permissions() {
+        let permissions = Permissions::from_config(&config(
+            &[],
+            &[("moderator", &[Permission::Status, Permission::Forgive])],
+            &[("1", "moderator")],
+        ));
+
+        assert_eq!(permissions.role("1"), Some("moderator"));
+        assert!(permissions.role_permits("moderator", Permission::Status));
+        assert!(permissions.role_permits("moderator", Permission::Forgive));
+        assert!(!permissions.role_permits("moderator", Permission::Start));
+        assert!(!permissions.role_permits("moderator", Permission::ManageSchedules));
+    }
+
+    #[test]
+    fn every_subcommand_requires_its_permission() {
+        assert_eq!(required("start mm"), Some(Permission::Start));
+        assert_eq!(required("end"), Some(Permission::End));
+        assert_eq!(required("status"), Some(Permission::Status));
+        assert_eq!(required("list"), Some(Permission::List));
+        assert_eq!(required("forgive someone"), Some(Permission::Forgive));
+        assert_eq!(required("history"), Some(Permission::History));
+        assert_eq!(required("schedule list"), Some(Permission::Schedule));
+        assert_eq!(
+            required("schedule add daily 20:00 mm"),
+            Some(Permission::ManageSchedules)
+        );
+        assert_eq!(
+            required("schedule on match-start mm"),
+            Some(Permission::ManageSchedules)
+        );
+        assert_eq!(
+            required("schedule remove 1"),
+            Some(Permission::ManageSchedules)
+        );
+        assert_eq!(required("reload"), Some(Permission::Reload));
+        assert_eq!(required("emergency-exit"), Some(Permission::EmergencyExit));
+        assert_eq!(required("help"), None);
+    }
+}
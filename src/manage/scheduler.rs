@@ -0,0 +1,679 @@
+use std::{
+    fmt::Display,
+    io,
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, TimeDelta, Utc, Weekday};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use super::command::{parse_start_event, StartEvent};
+use crate::game::MatchClock;
+
+/// A schedule as written in the config file. Either `days` and `time` or `trigger` are given.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ScheduleConfig {
+    /// See [`ScheduleDays`] for the accepted formats.
+    pub days: Option<String>,
+
+    /// The time of day in UTC, such as 20:00.
+    pub time: Option<String>,
+
+    /// See [`MatchTrigger`] for the accepted formats.
+    pub trigger: Option<String>,
+
+    /// The event as it would be given to the start command, such as "mm 2m 5m".
+    pub event: String,
+}
+
+/// Configures where schedules added through chat are kept.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SchedulerConfig {
+    #[serde(default = "default_path")]
+    pub path: PathBuf,
+}
+
+fn default_path() -> PathBuf {
+    PathBuf::from("hellvents_schedules.json")
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            path: default_path(),
+        }
+    }
+}
+
+/// The days on which a scheduled event starts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScheduleDays {
+    Daily,
+    Weekdays(Vec<Weekday>),
+    /// Only once on this date.
+    Date(NaiveDate),
+}
+
+impl FromStr for ScheduleDays {
+    type Err = String;
+
+    /// Accepts "daily", a list of weekdays such as "sat,sun" or a date such as "2026-10-24".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("daily") {
+            return Ok(ScheduleDays::Daily);
+        }
+
+        if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            return Ok(ScheduleDays::Date(date));
+        }
+
+        let weekdays = s
+            .split(',')
+            .map(|day| day.trim().parse::<Weekday>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("\"{}\" is neither daily, a list of weekdays nor a date", s))?;
+
+        Ok(ScheduleDays::Weekdays(weekdays))
+    }
+}
+
+impl Display for ScheduleDays {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleDays::Daily => f.write_str("Daily"),
+            ScheduleDays::Weekdays(weekdays) => {
+                let names: Vec<String> = weekdays.iter().map(|day| day.to_string()).collect();
+                f.write_str(&names.join(", "))
+            }
+            ScheduleDays::Date(date) => write!(f, "{}", date),
+        }
+    }
+}
+
+pub fn parse_days(s: &str) -> Result<ScheduleDays, String> {
+    s.parse()
+}
+
+pub fn parse_time(s: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(s, "%H:%M")
+        .map_err(|_| format!("\"{}\" is not a time such as 20:00", s))
+}
+
+/// A point in a match at which an event starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MatchTrigger {
+    /// When a new match starts.
+    MatchStart,
+    /// When this much time is left in the match.
+    Remaining(Duration),
+    /// When the first team holds this many sectors.
+    Score(u64),
+}
+
+impl FromStr for MatchTrigger {
+    type Err = String;
+
+    /// Accepts "match-start", "remaining:5m" or "score:4".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("match-start") {
+            return Ok(MatchTrigger::MatchStart);
+        }
+
+        match s.split_once(':') {
+            Some(("remaining", remaining)) => humantime::parse_duration(remaining)
+                .map(MatchTrigger::Remaining)
+                .map_err(|e| e.to_string()),
+            Some(("score", score)) => score
+                .parse()
+                .map(MatchTrigger::Score)
+                .map_err(|_| format!("\"{}\" is not a score", score)),
+            _ => Err(format!(
+                "\"{}\" is neither match-start, remaining:<time> nor score:<sectors>",
+                s
+            )),
+        }
+    }
+}
+
+impl Display for MatchTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchTrigger::MatchStart => f.write_str("At match start"),
+            MatchTrigger::Remaining(remaining) => write!(
+                f,
+                "With {} remaining",
+                humantime::format_duration(*remaining)
+            ),
+            MatchTrigger::Score(score) => write!(f, "At {} sectors", score),
+        }
+    }
+}
+
+pub fn parse_trigger(s: &str) -> Result<MatchTrigger, String> {
+    s.parse()
+}
+
+/// When a scheduled event starts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum When {
+    /// At a time of day in UTC.
+    Time { days: ScheduleDays, time: NaiveTime },
+    /// At a point in every match.
+    Match { trigger: MatchTrigger },
+}
+
+/// An event which is started automatically.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schedule {
+    #[serde(flatten)]
+    pub when: When,
+    pub event: StartEvent,
+}
+
+impl Schedule {
+    pub fn from_config(config: &ScheduleConfig) -> Result<Self, String> {
+        let event = parse_start_event(&config.event).map_err(|e| e.to_string())?;
+
+        let when = match (&config.days, &config.time, &config.trigger) {
+            (Some(days), Some(time), None) => When::Time {
+                days: parse_days(days)?,
+                time: parse_time(time)?,
+            },
+            (None, None, Some(trigger)) => When::Match {
+                trigger: parse_trigger(trigger)?,
+            },
+            _ => return Err("Either days and time or a trigger must be given".to_string()),
+        };
+
+        Ok(Self { when, event })
+    }
+
+    /// The first time the event starts after `after`, if it starts again at all. Always
+    /// [`None`] for match triggers, those depend on the game rather than the clock.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let When::Time { days, time } = &self.when else {
+            return None;
+        };
+
+        if let ScheduleDays::Date(date) = days {
+            let at = date.and_time(*time).and_utc();
+            return (at > after).then_some(at);
+        }
+
+        (0..=7)
+            .map(|offset| after.date_naive() + TimeDelta::days(offset))
+            .filter(|date| match days {
+                ScheduleDays::Weekdays(weekdays) => weekdays.contains(&date.weekday()),
+                _ => true,
+            })
+            .map(|date| date.and_time(*time).and_utc())
+            .find(|at| *at > after)
+    }
+
+    /// Whether the schedule will ever start its event again.
+    fn is_pending(&self, after: DateTime<Utc>) -> bool {
+        matches!(self.when, When::Match { .. }) || self.next_after(after).is_some()
+    }
+}
+
+impl Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.when {
+            When::Time { days, time } => write!(f, "{} {} UTC", days, time.format("%H:%M"))?,
+            When::Match { trigger } => write!(f, "{}", trigger)?,
+        }
+
+        write!(f, ": {}", self.event.name())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScheduleEntry {
+    pub id: u32,
+    pub schedule: Schedule,
+
+    /// Schedules from the config file cannot be removed through chat.
+    pub from_config: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoveOutcome {
+    Removed(Schedule),
+    FromConfig,
+    NotFound,
+}
+
+/// Keeps track of all schedules and when they are due.
+pub struct Scheduler {
+    path: PathBuf,
+    entries: Vec<ScheduleEntry>,
+    next_id: u32,
+
+    /// Everything due up to this point has already been started.
+    checked: DateTime<Utc>,
+
+    /// The time left in the match when triggers were last checked.
+    last_remaining: Option<Duration>,
+
+    /// The leading score when triggers were last checked.
+    last_score: Option<u64>,
+
+    /// Whether a match is underway, between the end of a match and the start of the next
+    /// the game state is meaningless.
+    in_match: bool,
+}
+
+impl Scheduler {
+    /// Build the scheduler from the schedules in the config and those persisted from chat.
+    /// Occurrences missed while hellvents was not running are skipped.
+    pub async fn load(config: &SchedulerConfig, schedules: &[ScheduleConfig]) -> Self {
+        let mut scheduler = Self {
+            path: config.path.clone(),
+            entries: Vec::new(),
+            next_id: 1,
+            checked: Utc::now(),
+            last_remaining: None,
+            last_score: None,
+            in_match: true,
+        };
+
+        for schedule in schedules {
+            match Schedule::from_config(schedule) {
+                Ok(schedule) => {
+                    scheduler.push(schedule, true);
+                }
+                Err(e) => warn!("Ignoring invalid schedule {:?}: {}", schedule, e),
+            }
+        }
+
+        match scheduler.read().await {
+            Ok(persisted) => {
+                for schedule in persisted {
+                    scheduler.push(schedule, false);
+                }
+            }
+            Err(e) => warn!("Failed to read persisted schedules: {}", e),
+        }
+
+        if scheduler.prune() {
+            scheduler.persist().await;
+        }
+
+        info!("Loaded {} schedules", scheduler.entries.len());
+        scheduler
+    }
+
+    pub fn entries(&self) -> &[ScheduleEntry] {
+        &self.entries
+    }
+
+    /// Whether any schedule depends on the game state.
+    pub fn has_match_triggers(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| matches!(entry.schedule.when, When::Match { .. }))
+    }
+
+    /// When the next scheduled event is due.
+    pub fn next_due(&self) -> Option<DateTime<Utc>> {
+        self.entries
+            .iter()
+            .filter_map(|entry| entry.schedule.next_after(self.checked))
+            .min()
+    }
+
+    /// Take the events which have become due since the last call.
+    pub async fn take_due(&mut self) -> Vec<StartEvent> {
+        let now = Utc::now();
+        let due = self
+            .entries
+            .iter()
+            .filter(|entry| {
+                entry
+                    .schedule
+                    .next_after(self.checked)
+                    .is_some_and(|at| at <= now)
+            })
+            .map(|entry| entry.schedule.event.clone())
+            .collect();
+
+        self.checked = now;
+        if self.prune() {
+            self.persist().await;
+        }
+
+        due
+    }
+
+    /// Forget the progress of the previous match and take the events triggered by the start
+    /// of a new one.
+    pub fn match_started(&mut self) -> Vec<StartEvent> {
+        self.last_remaining = None;
+        self.last_score = None;
+        self.in_match = true;
+        self.triggered(|trigger| trigger == MatchTrigger::MatchStart)
+    }
+
+    /// Stop firing triggers until the next match has started.
+    pub fn match_ended(&mut self) {
+        self.in_match = false;
+    }
+
+    /// Take over what the scheduler being replaced knows about the current match and time, so
+    /// nothing fires twice or between matches.
+    pub fn continue_from(&mut self, previous: &Scheduler) {
+        self.checked = previous.checked;
+        self.last_remaining = previous.last_remaining;
+        self.last_score = previous.last_score;
+        self.in_match = previous.in_match;
+    }
+
+    /// When the next trigger on the remaining time of the match is due.
+    pub fn next_match_due(&self, clock: &MatchClock) -> Option<Instant> {
+        if !self.in_match {
+            return None;
+        }
+
+        self.entries
+            .iter()
+            .filter_map(|entry| match entry.schedule.when {
+                When::Match {
+                    trigger: MatchTrigger::Remaining(remaining),
+                } => clock.at_remaining(remaining),
+                _ => None,
+            })
+            .min()
+    }
+
+    /// Take the events whose trigger has been passed since the last call. Triggers only fire
+    /// when they are passed while hellvents watches, never for the progress of a match which
+    /// was already underway when it started watching.
+    pub fn take_match_triggered(&mut self, clock: &MatchClock) -> Vec<StartEvent> {
+        if !self.in_match {
+            return Vec::new();
+        }
+
+        let remaining = clock.remaining(Instant::now());
+        let score = clock.state.leading_score();
+        let (last_remaining, last_score) = (self.last_remaining, self.last_score);
+
+        self.last_remaining = Some(remaining);
+        self.last_score = Some(score);
+
+        self.triggered(|trigger| match trigger {
+            MatchTrigger::MatchStart => false,
+            MatchTrigger::Remaining(at) => {
+                last_remaining.is_some_and(|last| last > at) && remaining <= at
+            }
+            MatchTrigger::Score(at) => last_score.is_some_and(|last| last < at) && score >= at,
+        })
+    }
+
+    fn triggered(&self, fires: impl Fn(MatchTrigger) -> bool) -> Vec<StartEvent> {
+        self.entries
+            .iter()
+            .filter(|entry| match entry.schedule.when {
+                When::Match { trigger } => fires(trigger),
+                _ => false,
+            })
+            .map(|entry| entry.schedule.event.clone())
+            .collect()
+    }
+
+    pub async fn add(&mut self, schedule: Schedule) -> u32 {
+        let id = self.push(schedule, false);
+        self.persist().await;
+        id
+    }
+
+    pub async fn remove(&mut self, id: u32) -> RemoveOutcome {
+        let Some(position) = self.entries.iter().position(|entry| entry.id == id) else {
+            return RemoveOutcome::NotFound;
+        };
+
+        if self.entries[position].from_config {
+            return RemoveOutcome::FromConfig;
+        }
+
+        let entry = self.entries.remove(position);
+        self.persist().await;
+        RemoveOutcome::Removed(entry.schedule)
+    }
+
+    fn push(&mut self, schedule: Schedule, from_config: bool) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entries.push(ScheduleEntry {
+            id,
+            schedule,
+            from_config,
+        });
+        id
+    }
+
+    /// Drop schedules which will never start again. Returns whether anything was dropped.
+    fn prune(&mut self) -> bool {
+        let before = self.entries.len();
+        let checked = self.checked;
+        self.entries
+            .retain(|entry| entry.schedule.is_pending(checked));
+        self.entries.len() != before
+    }
+
+    async fn read(&self) -> io::Result<Vec<Schedule>> {
+        let content = match tokio::fs::read_to_string(&self.path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    async fn persist(&self) {
+        let schedules: Vec<&Schedule> = self
+            .entries
+            .iter()
+            .filter(|entry| !entry.from_config)
+            .map(|entry| &entry.schedule)
+            .collect();
+
+        let result = async {
+            let content = serde_json::to_vec_pretty(&schedules)?;
+            tokio::fs::write(&self.path, content).await
+        };
+
+        if let Err(e) = result.await {
+            warn!("Failed to persist schedules: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameState;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    fn schedule(days: ScheduleDays, time: &str) -> Schedule {
+        Schedule {
+            when: When::Time {
+                days,
+                time: parse_time(time).unwrap(),
+            },
+            event: StartEvent::RadioSpies {},
+        }
+    }
+
+    #[test]
+    fn daily_starts_today_or_tomorrow() {
+        let daily = schedule(ScheduleDays::Daily, "20:00");
+
+        assert_eq!(
+            daily.next_after(at("2026-10-17T12:00:00Z")),
+            Some(at("2026-10-17T20:00:00Z"))
+        );
+        // An occurrence exactly at `after` has already happened.
+        assert_eq!(
+            daily.next_after(at("2026-10-17T20:00:00Z")),
+            Some(at("2026-10-18T20:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn weekdays_skip_other_days() {
+        let weekend = schedule(parse_days("sat,sun").unwrap(), "20:00");
+
+        // 2026-10-17 is a Saturday.
+        assert_eq!(
+            weekend.next_after(at("2026-10-17T21:00:00Z")),
+            Some(at("2026-10-18T20:00:00Z"))
+        );
+        assert_eq!(
+            weekend.next_after(at("2026-10-18T21:00:00Z")),
+            Some(at("2026-10-24T20:00:00Z"))
+        );
+
+        // A single weekday which just passed comes around a week later.
+        let saturday = schedule(parse_days("sat").unwrap(), "20:00");
+        assert_eq!(
+            saturday.next_after(at("2026-10-17T20:30:00Z")),
+            Some(at("2026-10-24T20:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn date_starts_only_once() {
+        let once = schedule(parse_days("2026-10-24").unwrap(), "20:00");
+
+        assert_eq!(
+            once.next_after(at("2026-10-17T12:00:00Z")),
+            Some(at("2026-10-24T20:00:00Z"))
+        );
+        assert_eq!(once.next_after(at("2026-10-24T20:00:00Z")), None);
+    }
+
+    #[test]
+    fn parses_triggers() {
+        assert_eq!(parse_trigger("match-start"), Ok(MatchTrigger::MatchStart));
+        assert_eq!(
+            parse_trigger("remaining:5m"),
+            Ok(MatchTrigger::Remaining(Duration::from_secs(5 * 60)))
+        );
+        assert_eq!(parse_trigger("score:4"), Ok(MatchTrigger::Score(4)));
+
+        assert!(parse_trigger("score:many").is_err());
+        assert!(parse_trigger("remaining:soon").is_err());
+        assert!(parse_trigger("halftime").is_err());
+    }
+
+    fn scheduler(triggers: &[MatchTrigger]) -> Scheduler {
+        let mut scheduler = Scheduler {
+            path: PathBuf::new(),
+            entries: Vec::new(),
+            next_id: 1,
+            checked: Utc::now(),
+            last_remaining: None,
+            last_score: None,
+            in_match: true,
+        };
+
+        for trigger in triggers {
+            scheduler.push(
+                Schedule {
+                    when: When::Match { trigger: *trigger },
+                    event: StartEvent::RadioSpies {},
+                },
+                true,
+            );
+        }
+
+        scheduler
+    }
+
+    fn clock(remaining_minutes: u64, allied_score: u64) -> MatchClock {
+        MatchClock::new(GameState {
+            allied_score,
+            axis_score: 2,
+            remaining: Duration::from_secs(remaining_minutes * 60),
+            map: "foy_warfare".to_string(),
+        })
+    }
+
+    #[test]
+    fn remaining_trigger_fires_once_when_crossed() {
+        let mut scheduler = scheduler(&[MatchTrigger::Remaining(Duration::from_secs(5 * 60))]);
+
+        assert!(scheduler.take_match_triggered(&clock(10, 2)).is_empty());
+        assert_eq!(scheduler.take_match_triggered(&clock(5, 2)).len(), 1);
+        assert!(scheduler.take_match_triggered(&clock(4, 2)).is_empty());
+    }
+
+    #[test]
+    fn trigger_passed_before_watching_does_not_fire() {
+        let mut scheduler = scheduler(&[MatchTrigger::Remaining(Duration::from_secs(5 * 60))]);
+
+        // The first measurement only establishes where the match is.
+        assert!(scheduler.take_match_triggered(&clock(3, 2)).is_empty());
+        assert!(scheduler.take_match_triggered(&clock(2, 2)).is_empty());
+    }
+
+    #[test]
+    fn score_trigger_fires_once_when_crossed() {
+        let mut scheduler = scheduler(&[MatchTrigger::Score(4)]);
+
+        assert!(scheduler.take_match_triggered(&clock(60, 3)).is_empty());
+        assert_eq!(scheduler.take_match_triggered(&clock(59, 4)).len(), 1);
+        assert!(scheduler.take_match_triggered(&clock(58, 4)).is_empty());
+        assert!(scheduler.take_match_triggered(&clock(57, 5)).is_empty());
+    }
+
+    #[test]
+    fn nothing_fires_between_matches() {
+        let mut scheduler = scheduler(&[
+            MatchTrigger::Remaining(Duration::from_secs(5 * 60)),
+            MatchTrigger::Score(4),
+        ]);
+
+        assert!(scheduler.take_match_triggered(&clock(10, 3)).is_empty());
+        scheduler.match_ended();
+        assert!(scheduler.take_match_triggered(&clock(1, 5)).is_empty());
+        assert_eq!(scheduler.next_match_due(&clock(10, 3)), None);
+
+        // The progress of the old match does not carry into the new one.
+        scheduler.match_started();
+        assert!(scheduler.take_match_triggered(&clock(1, 5)).is_empty());
+    }
+
+    #[test]
+    fn match_start_trigger_fires_on_match_start() {
+        let mut scheduler = scheduler(&[MatchTrigger::MatchStart, MatchTrigger::Score(4)]);
+
+        assert_eq!(scheduler.match_started(), vec![StartEvent::RadioSpies {}]);
+        assert!(scheduler.take_match_triggered(&clock(60, 0)).is_empty());
+    }
+
+    #[test]
+    fn replacement_continues_the_match() {
+        let mut previous = scheduler(&[MatchTrigger::Score(4)]);
+        previous.take_match_triggered(&clock(10, 3));
+
+        let mut replacement = scheduler(&[MatchTrigger::Score(4)]);
+        replacement.continue_from(&previous);
+        assert_eq!(replacement.take_match_triggered(&clock(9, 4)).len(), 1);
+
+        previous.match_ended();
+        let mut replacement = scheduler(&[MatchTrigger::Score(4)]);
+        replacement.continue_from(&previous);
+        assert!(replacement.take_match_triggered(&clock(9, 4)).is_empty());
+    }
+}
@@ -1,58 +1,699 @@
 pub mod melee_mania {
     use std::time::Duration;
 
-    pub fn info_message(delay: &Duration, duration: &Duration) -> String {
+    use crate::{
+        penalties::{KillKind, PenaltyAction, PenaltyLadder},
+        weapons::WeaponRule,
+    };
+
+    pub fn info_message(
+        name: &str,
+        rule: &WeaponRule,
+        ladder: &PenaltyLadder,
+        delay: &Duration,
+        duration: &Duration,
+    ) -> String {
         format!(
             "HELLVENTS | INFO\n
-The mini game MELEE MANIA will start in {}. \
-For a period of {}, only melee weapons will be allowed.\n
+The mini game {} will start in {}. \
+For a period of {}, the {} rule applies. {}\n
 You will receive a message when the mini game has started and ended.\n
 Invalid kills result in penalities!
-1 & 2 Invalid Kills: Forced Redeploy
-3+ Invalid Kills: Kick",
+{}",
+            name.to_uppercase(),
             humantime::format_duration(*delay),
-            humantime::format_duration(*duration)
+            humantime::format_duration(*duration),
+            rule,
+            rule.description(),
+            penalty_rules(ladder)
         )
     }
 
-    pub fn start_message(duration: &Duration) -> String {
+    pub fn start_message(
+        name: &str,
+        rule: &WeaponRule,
+        ladder: &PenaltyLadder,
+        duration: &Duration,
+    ) -> String {
         format!(
             "HELLVENTS | START\n
-The mini game MELEE MANIA has started. \
-For a period of {}, only melee weapons will be allowed.\n
+The mini game {} has started. \
+For a period of {}, the {} rule applies. {}\n
 You will receive a message when the mini game has ended.\n
 Invalid kills result in penalities!
-1 & 2 Invalid Kills: Forced Redeploy
-3+ Invalid Kills: Kick",
-            humantime::format_duration(*duration)
+{}",
+            name.to_uppercase(),
+            humantime::format_duration(*duration),
+            rule,
+            rule.description(),
+            penalty_rules(ladder)
         )
     }
 
-    pub fn running_message(remaining: &Duration) -> String {
+    pub fn running_message(
+        name: &str,
+        rule: &WeaponRule,
+        ladder: &PenaltyLadder,
+        remaining: &Duration,
+    ) -> String {
         format!(
-            "HELLVENTS | RUNNING\n\nThe mini game MELEE MANIA is currently running.\n
-For a period of {}, only melee weapons will be allowed.\n
+            "HELLVENTS | RUNNING\n\nThe mini game {} is currently running.\n
+For a period of {}, the {} rule applies. {}\n
 You will receive a message when the mini game has ended.\n
 Invalid kills result in penalities!
-1 & 2 Invalid Kills: Forced Redeploy
-3+ Invalid Kills: Kick",
-            humantime::format_duration(*remaining)
+{}",
+            name.to_uppercase(),
+            humantime::format_duration(*remaining),
+            rule,
+            rule.description(),
+            penalty_rules(ladder)
         )
     }
 
-    pub fn end_message() -> String {
+    pub fn end_message(name: &str) -> String {
         format!(
             "HELLVENTS | END\n
-The mini game MELEE MANIA has ended.\n
+The mini game {} has ended.\n
 Thanks for participating.\n
 -----\n{}",
+            name.to_uppercase(),
             open_source_disclaimer()
         )
     }
 
+    /// The penalty ladder as shown to players, one line per step.
+    pub fn penalty_rules(ladder: &PenaltyLadder) -> String {
+        let lines: Vec<String> = ladder
+            .ranges()
+            .into_iter()
+            .map(|(first, last, action)| {
+                let infractions = match last {
+                    None => format!("{}+ Invalid Kills", first),
+                    Some(last) if last == first => match first {
+                        1 => "1 Invalid Kill".to_string(),
+                        _ => format!("{} Invalid Kills", first),
+                    },
+                    Some(last) if last == first + 1 => {
+                        format!("{} & {} Invalid Kills", first, last)
+                    }
+                    Some(last) => format!("{}-{} Invalid Kills", first, last),
+                };
+                format!("{}: {}", infractions, action)
+            })
+            .collect();
+
+        lines.join("\n")
+    }
+
+    /// Tell a player which penalty they received for their `infraction`th invalid kill.
+    /// `breaks_rule` is false for kills which are penalized regardless of the weapon.
+    pub fn penalty_message(
+        action: &PenaltyAction,
+        infraction: u32,
+        kind: KillKind,
+        weapon: &str,
+        rule: &WeaponRule,
+        breaks_rule: bool,
+    ) -> String {
+        let violation = match (breaks_rule, kind) {
+            (true, KillKind::Enemy) => {
+                format!("Your kill with {} violated the {} rule.", weapon, rule)
+            }
+            (true, KillKind::Teamkill) => {
+                format!("Your teamkill with {} violated the {} rule.", weapon, rule)
+            }
+            (true, KillKind::Suicide) => {
+                format!("Your suicide with {} violated the {} rule.", weapon, rule)
+            }
+            (true, KillKind::Roadkill) => {
+                format!("Your roadkill with {} violated the {} rule.", weapon, rule)
+            }
+            (true, KillKind::Environment) => {
+                format!("Your kill by {} violated the {} rule.", weapon, rule)
+            }
+            (false, KillKind::Teamkill) => {
+                "Teamkills are not allowed during this event.".to_string()
+            }
+            (false, KillKind::Suicide) => "Suicides are not allowed during this event.".to_string(),
+            (false, KillKind::Roadkill) => {
+                "Roadkills are not allowed during this event.".to_string()
+            }
+            (false, _) => "Environmental kills are not allowed during this event.".to_string(),
+        };
+
+        let consequence = match action {
+            PenaltyAction::Warn if breaks_rule => format!(
+                "{} This is a warning, further invalid kills will be penalized.",
+                rule.description()
+            ),
+            PenaltyAction::Warn => {
+                "This is a warning, further invalid kills will be penalized.".to_string()
+            }
+            PenaltyAction::Punish if breaks_rule => rule.description(),
+            PenaltyAction::Punish => "You have been redeployed.".to_string(),
+            PenaltyAction::Kick => {
+                format!("You have been kicked after {} invalid kills.", infraction)
+            }
+            PenaltyAction::TempBan { hours } => format!(
+                "You have been banned for {} hours after {} invalid kills.",
+                hours, infraction
+            ),
+        };
+
+        format!("{} {}", violation, consequence)
+    }
+
+    /// Tell the victim of an invalid kill what happened to their killer. There is nobody to
+    /// tell about a suicide.
+    pub fn victim_message(
+        action: &PenaltyAction,
+        kind: KillKind,
+        killer_name: &str,
+        weapon: &str,
+    ) -> Option<String> {
+        let outcome = match action {
+            PenaltyAction::Warn => "warned",
+            PenaltyAction::Punish => "redeployed",
+            PenaltyAction::Kick => "kicked",
+            PenaltyAction::TempBan { .. } => "temporarily banned",
+        };
+
+        let (killer, deed) = match kind {
+            KillKind::Suicide => return None,
+            KillKind::Teamkill => ("teammate", "killing you"),
+            KillKind::Roadkill => ("driver", "running you over"),
+            KillKind::Enemy | KillKind::Environment => ("killer", "killing you"),
+        };
+
+        Some(format!(
+            "Your {} {} has been {} for {} with {}.",
+            killer, killer_name, outcome, deed, weapon
+        ))
+    }
+
     pub fn open_source_disclaimer() -> &'static str {
         "Hellvents is open source and freely available for use, from the community for it!\n
 GitHub:
 https://github.com/Lelleck/hellvents"
     }
 }
+
+pub mod sky_eye {
+    use std::time::Duration;
+
+    use wise_api::rcon::parsing::Player;
+
+    use super::melee_mania::open_source_disclaimer;
+
+    pub fn start_message(cam_count: usize, rotation: &Duration) -> String {
+        format!(
+            "HELLVENTS | START\n
+The mini game SKY EYE has started. \
+{} players of each team receive admin cam access.\n
+Every {} the access is handed to other players. \
+You will receive a message when it is your turn.",
+            cam_count,
+            humantime::format_duration(*rotation)
+        )
+    }
+
+    pub fn granted_message(granted: &[(Player, String)]) -> String {
+        let mut message = String::from("HELLVENTS | SKY EYE\n\nAdmin cam access granted to:\n");
+        for (player, team) in granted {
+            message.push_str(&format!("{} ({})\n", player.name, team));
+        }
+
+        message
+    }
+
+    pub fn end_message() -> String {
+        format!(
+            "HELLVENTS | END\n
+The mini game SKY EYE has ended. All admin cam access has been revoked.\n
+Thanks for participating.\n
+-----\n{}",
+            open_source_disclaimer()
+        )
+    }
+}
+
+pub mod admin {
+    use std::time::Duration;
+
+    use chrono::{DateTime, Utc};
+    use clap::error::ErrorKind;
+
+    use crate::{
+        events::EventStatus,
+        history::EventRun,
+        manage::{
+            command::{CommandHelp, EventDescription, StartEvent},
+            permissions::Permission,
+            scheduler::{Schedule, ScheduleEntry},
+        },
+        weapons::WeaponRule,
+    };
+
+    /// Roughly how many characters fit into the message box without it getting cut off.
+    const MAX_MESSAGE_LENGTH: usize = 450;
+
+    pub fn event_started_message(
+        id: u32,
+        start: &StartEvent,
+        stopped: &[(u32, &'static str)],
+    ) -> String {
+        let mut message = format!(
+            "HELLVENTS | START\n\nStarted #{} {}\n{}",
+            id,
+            start.name(),
+            start_config(start)
+        );
+
+        for (id, name) in stopped {
+            message.push_str(&format!("\nEnded #{} {} as it conflicts", id, name));
+        }
+
+        message
+    }
+
+    /// Describe how an event was configured in a single line.
+    fn start_config(start: &StartEvent) -> String {
+        let timing = |delay: &Duration, duration: &Duration| {
+            format!(
+                "Starts in {}, lasts {}",
+                humantime::format_duration(*delay),
+                humantime::format_duration(*duration)
+            )
+        };
+
+        match start {
+            StartEvent::MeleeMania { delay, duration } => timing(delay, duration),
+            StartEvent::RestrictedArsenal {
+                delay,
+                duration,
+                allow,
+                deny,
+            } => {
+                let rule = if allow.is_empty() {
+                    WeaponRule::Deny(deny.clone())
+                } else {
+                    WeaponRule::Allow(allow.clone())
+                };
+                format!("{}, {}", timing(delay, duration), rule)
+            }
+            StartEvent::SkyEye {
+                cam_count,
+                rotation,
+            } => format!(
+                "{} cams per team, rotating every {}",
+                cam_count,
+                humantime::format_duration(*rotation)
+            ),
+            StartEvent::RadioSpies {} => "Starts right away".to_string(),
+        }
+    }
+
+    pub fn events_ended_message(ended: &[(u32, &'static str, Duration)]) -> String {
+        if ended.is_empty() {
+            return "HELLVENTS | END\n\nNo event is currently running.".to_string();
+        }
+
+        let mut message = String::from("HELLVENTS | END\n");
+        for (id, name, ran_for) in ended {
+            let ran_for = Duration::from_secs(ran_for.as_secs());
+            message.push_str(&format!(
+                "\nEnded #{} {} after {}",
+                id,
+                name,
+                humantime::format_duration(ran_for)
+            ));
+        }
+
+        message
+    }
+
+    /// Render a parse error or help short enough to read in game, without blank lines and
+    /// the hint to use help.
+    pub fn command_error_message(error: &clap::Error) -> String {
+        let rendered = error
+            .render()
+            .to_string()
+            // Commands are issued through the chat command, not the binary.
+            .replace("Usage: hellvents", "Usage: /hv");
+        let lines = rendered
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty());
+
+        let (title, lines): (_, Vec<String>) = match error.kind() {
+            ErrorKind::DisplayHelp
+            | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand
+            | ErrorKind::DisplayVersion => ("HELP", lines.map(condense).collect()),
+            _ => (
+                "ERROR",
+                lines
+                    .filter(|line| !line.starts_with("For more information"))
+                    .map(|line| line.trim_start_matches("error: ").to_string())
+                    .collect(),
+            ),
+        };
+
+        truncate(format!("HELLVENTS | {}\n\n{}", title, lines.join("\n")))
+    }
+
+    /// Replace the padding between a name and its description with a colon.
+    fn condense(line: &str) -> String {
+        match line.split_once("  ") {
+            Some((name, description)) => format!("{}: {}", name, description.trim()),
+            None => line.to_string(),
+        }
+    }
+
+    /// Show a page of help.
+    pub fn help_message(help: &CommandHelp, topic: &[String], page: usize) -> String {
+        let mut header = help.usage.clone();
+        if !help.about.is_empty() {
+            header.push_str(&format!("\n{}", help.about));
+        }
+
+        let mut more = vec!["/hv help".to_string()];
+        more.extend(topic.iter().cloned());
+        paginate("HELP", Some(&header), &help.entries, page, &more.join(" "))
+    }
+
+    /// Spread the entries across pages which each fit into the message box and show one of them.
+    /// `more` is the command showing a page once the page number is appended.
+    fn paginate(
+        title: &str,
+        header: Option<&str>,
+        entries: &[String],
+        page: usize,
+        more: &str,
+    ) -> String {
+        // Leave room for the title and the hint to the next page.
+        let header_length = header.map_or(0, |header| header.len());
+        let budget = MAX_MESSAGE_LENGTH.saturating_sub(header_length + 80);
+        let mut pages: Vec<Vec<&str>> = vec![Vec::new()];
+        let mut length = 0;
+        for entry in entries {
+            let current = pages.last_mut().unwrap();
+            if !current.is_empty() && length + entry.len() + 1 > budget {
+                pages.push(vec![entry]);
+                length = entry.len() + 1;
+            } else {
+                current.push(entry);
+                length += entry.len() + 1;
+            }
+        }
+
+        let total = pages.len();
+        let page = page.clamp(1, total);
+        let mut message = format!("HELLVENTS | {} {}/{}\n", title, page, total);
+        if let Some(header) = header {
+            message.push_str(&format!("\n{}\n", header));
+        }
+        for entry in &pages[page - 1] {
+            message.push_str(&format!("\n{}", entry));
+        }
+
+        if page < total {
+            message.push_str(&format!("\n\nMore: {} {}", more, page + 1));
+        }
+
+        truncate(message)
+    }
+
+    pub fn reload_message(changed: &[&str], restart: &[&str]) -> String {
+        if changed.is_empty() && restart.is_empty() {
+            return "HELLVENTS | RELOAD\n\nNothing has changed.".to_string();
+        }
+
+        let mut message = String::from("HELLVENTS | RELOAD\n");
+        if !changed.is_empty() {
+            message.push_str(&format!(
+                "\nApplied: {}\nRunning events keep their settings.",
+                changed.join(", ")
+            ));
+        }
+        if !restart.is_empty() {
+            message.push_str(&format!("\nNeeds a restart: {}", restart.join(", ")));
+        }
+
+        message
+    }
+
+    pub fn emergency_exit_message() -> String {
+        "HELLVENTS | EMERGENCY EXIT\n\nHellvents is exiting now, running events are abandoned."
+            .to_string()
+    }
+
+    pub fn reload_failed_message(error: &str) -> String {
+        truncate(format!(
+            "HELLVENTS | RELOAD\n\nThe config was not reloaded:\n{}",
+            error
+        ))
+    }
+
+    pub fn help_unknown_message(topic: &[String]) -> String {
+        format!(
+            "HELLVENTS | HELP\n\nThere is no command \"{}\", see /hv help.",
+            topic.join(" ")
+        )
+    }
+
+    fn truncate(mut message: String) -> String {
+        if message.len() <= MAX_MESSAGE_LENGTH {
+            return message;
+        }
+
+        let mut end = MAX_MESSAGE_LENGTH - 3;
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        message.truncate(end);
+        message.push_str("...");
+        message
+    }
+
+    /// `missed` is the amount of server messages hellvents missed by falling behind.
+    pub fn status_message(events: &[(u32, EventStatus)], missed: u64, page: usize) -> String {
+        let mut entries: Vec<String> = events
+            .iter()
+            .map(|(id, status)| format!("#{} {}", id, status))
+            .collect();
+
+        if missed > 0 {
+            entries.push(format!("Missed server messages: {}", missed));
+        }
+
+        paginate("STATUS", None, &entries, page, "/hv status")
+    }
+
+    pub fn no_event_message() -> String {
+        "HELLVENTS | STATUS\n\nNo event is currently running.".to_string()
+    }
+
+    pub fn permission_denied_message(role: &str, permission: Permission) -> String {
+        format!(
+            "HELLVENTS | PERMISSION\n\nYour role {} does not have the {} permission.",
+            role, permission
+        )
+    }
+
+    pub fn event_not_found_message(id: u32) -> String {
+        format!("HELLVENTS | END\n\nNo event with id #{} is running.", id)
+    }
+
+    /// Summarize past event runs, the most recent first. `count` is how many runs were asked
+    /// for, it is repeated in the hint to the next page.
+    pub fn history_message(runs: &[EventRun], count: usize, page: usize) -> String {
+        if runs.is_empty() {
+            return "HELLVENTS | HISTORY\n\nNo events have been run yet.".to_string();
+        }
+
+        let mut entries = Vec::new();
+        for run in runs.iter().rev() {
+            let mut entry = format!(
+                "{} ({})\n",
+                run.name,
+                run.started.format("%Y-%m-%d %H:%M UTC")
+            );
+
+            match run.ended {
+                Some(ended) => {
+                    let lasted = (ended - run.started).to_std().unwrap_or_default();
+                    let lasted = Duration::from_secs(lasted.as_secs());
+                    entry.push_str(&format!(
+                        "Lasted {} with {} players, {} penalties\n",
+                        humantime::format_duration(lasted),
+                        run.participants.len(),
+                        run.penalties.len()
+                    ));
+                }
+                None => entry.push_str(&format!(
+                    "Did not end properly, {} penalties\n",
+                    run.penalties.len()
+                )),
+            }
+            entries.push(entry);
+        }
+
+        let more = format!("/hv history {}", count);
+        paginate("HISTORY", None, &entries, page, &more)
+    }
+
+    pub fn schedule_list_message(entries: &[ScheduleEntry], page: usize) -> String {
+        if entries.is_empty() {
+            return "HELLVENTS | SCHEDULE\n\nNo events are scheduled.".to_string();
+        }
+
+        let entries: Vec<String> = entries
+            .iter()
+            .map(|entry| {
+                let mut line = format!("#{} {}", entry.id, entry.schedule);
+                if entry.from_config {
+                    line.push_str(" (config)");
+                }
+                line
+            })
+            .collect();
+
+        paginate("SCHEDULE", None, &entries, page, "/hv schedule list")
+    }
+
+    /// `next` is when a time based schedule starts next, match based schedules have none.
+    pub fn schedule_added_message(
+        id: u32,
+        schedule: &Schedule,
+        next: Option<DateTime<Utc>>,
+    ) -> String {
+        let next = match next {
+            Some(next) => format!("It next starts {}.", next.format("%Y-%m-%d %H:%M UTC")),
+            None => "It starts during every match.".to_string(),
+        };
+
+        format!(
+            "HELLVENTS | SCHEDULE\n\nScheduled #{} {}\n{}",
+            id, schedule, next
+        )
+    }
+
+    pub fn schedule_passed_message(schedule: &Schedule) -> String {
+        format!(
+            "HELLVENTS | SCHEDULE\n\nNot scheduled {}\nIts date has already passed.",
+            schedule
+        )
+    }
+
+    pub fn schedule_removed_message(id: u32, schedule: &Schedule) -> String {
+        format!("HELLVENTS | SCHEDULE\n\nRemoved #{} {}", id, schedule)
+    }
+
+    pub fn schedule_from_config_message(id: u32) -> String {
+        format!(
+            "HELLVENTS | SCHEDULE\n\n#{} is defined in the config file and cannot be removed here.",
+            id
+        )
+    }
+
+    pub fn schedule_not_found_message(id: u32) -> String {
+        format!("HELLVENTS | SCHEDULE\n\nThere is no schedule #{}.", id)
+    }
+
+    pub fn history_unavailable_message() -> String {
+        "HELLVENTS | HISTORY\n\nThe history could not be read.".to_string()
+    }
+
+    pub fn forgiven_message(player: &str) -> String {
+        format!(
+            "HELLVENTS | FORGIVE\n\nThe infractions of {} have been reset.",
+            player
+        )
+    }
+
+    pub fn forgiven_player_message() -> String {
+        "HELLVENTS | FORGIVE\n\nAn admin has forgiven all your infractions.".to_string()
+    }
+
+    pub fn no_infractions_message(player: &str) -> String {
+        format!(
+            "HELLVENTS | FORGIVE\n\nNo player named {} has any infractions.",
+            player
+        )
+    }
+
+    pub fn forgive_unsupported_message(event: &str) -> String {
+        format!(
+            "HELLVENTS | FORGIVE\n\n{} does not track infractions.",
+            event
+        )
+    }
+
+    pub fn list_message(events: &[EventDescription], page: usize) -> String {
+        let entries: Vec<String> = events
+            .iter()
+            .map(|event| {
+                let mut entry = event.name.clone();
+                if !event.aliases.is_empty() {
+                    entry.push_str(&format!(" ({})", event.aliases.join(", ")));
+                }
+                entry.push_str(&format!("\n{}\n", event.about));
+                entry
+            })
+            .collect();
+
+        paginate("EVENTS", None, &entries, page, "/hv list")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::admin::*;
+    use crate::events::{EventPhase, EventStatus};
+
+    fn statuses(count: u32) -> Vec<(u32, EventStatus)> {
+        (1..=count)
+            .map(|id| {
+                let counters = vec![("Penalties", 3), ("Missed log messages", 0)];
+                let status = EventStatus::new("Melee Mania", EventPhase::Running, None, counters);
+                (id, status)
+            })
+            .collect()
+    }
+
+    /// The total in a title such as "HELLVENTS | STATUS 1/3".
+    fn page_count(message: &str) -> usize {
+        let title = message.lines().next().unwrap();
+        title.rsplit('/').next().unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn short_status_fits_one_page() {
+        let message = status_message(&statuses(1), 0, 1);
+
+        assert!(message.starts_with("HELLVENTS | STATUS 1/1\n"));
+        assert!(!message.contains("More:"));
+    }
+
+    #[test]
+    fn long_status_is_paginated() {
+        let first = status_message(&statuses(20), 7, 1);
+        assert!(first.len() <= 450);
+        assert!(first.contains("More: /hv status 2"));
+
+        let last = status_message(&statuses(20), 7, page_count(&first));
+        assert!(last.len() <= 450);
+        assert!(last.ends_with("Missed server messages: 7"));
+        assert!(!last.contains("More:"));
+    }
+
+    #[test]
+    fn page_beyond_the_last_shows_the_last() {
+        let first = status_message(&statuses(20), 0, 1);
+
+        assert_eq!(
+            status_message(&statuses(20), 0, 100),
+            status_message(&statuses(20), 0, page_count(&first))
+        );
+    }
+}
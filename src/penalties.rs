@@ -0,0 +1,462 @@
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// What happens to a player for an infraction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum PenaltyAction {
+    /// Only message the player.
+    Warn,
+    /// Kill the player and force them to redeploy.
+    Punish,
+    Kick,
+    /// Ban the player for a number of hours, the game does not support anything shorter.
+    TempBan {
+        hours: u64,
+    },
+}
+
+impl PenaltyAction {
+    /// The ban duration of a [`PenaltyAction::TempBan`].
+    pub fn ban_duration(&self) -> Option<Duration> {
+        match self {
+            PenaltyAction::TempBan { hours } => Some(Duration::from_secs(hours * 60 * 60)),
+            _ => None,
+        }
+    }
+}
+
+impl Display for PenaltyAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PenaltyAction::Warn => f.write_str("Warning"),
+            PenaltyAction::Punish => f.write_str("Forced Redeploy"),
+            PenaltyAction::Kick => f.write_str("Kick"),
+            PenaltyAction::TempBan { hours } => write!(f, "{}h Ban", hours),
+        }
+    }
+}
+
+/// A step of the ladder which applies to a number of consecutive infractions.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PenaltyStep {
+    #[serde(flatten)]
+    pub action: PenaltyAction,
+
+    /// For how many infractions this step applies. The last step applies to all further ones.
+    #[serde(default = "default_times")]
+    pub times: u32,
+}
+
+fn default_times() -> u32 {
+    1
+}
+
+impl PenaltyStep {
+    /// Why the step cannot be part of a ladder, if it cannot.
+    pub fn problem(&self) -> Option<&'static str> {
+        if self.times == 0 {
+            return Some("applies to no infractions");
+        }
+
+        match self.action {
+            PenaltyAction::TempBan { hours: 0 } => Some("bans for 0 hours"),
+            _ => None,
+        }
+    }
+}
+
+/// The circumstances of a kill, which decide the [`KillPolicy`] that applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KillKind {
+    Enemy,
+    Teamkill,
+    Suicide,
+    /// Run over by a vehicle, the weapon of the kill is nothing but a vehicle.
+    Roadkill,
+    /// Killed by the environment rather than a weapon, such as by falling.
+    Environment,
+}
+
+/// How kills other than those of enemies are treated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KillPolicy {
+    /// The kill is never penalized.
+    Ignore,
+    /// The kill is penalized when it breaks the rules of the event, just like enemy kills.
+    Enforce,
+    /// The kill is always penalized, regardless of the weapon used.
+    Penalize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct KillPolicies {
+    pub teamkills: KillPolicy,
+    pub suicides: KillPolicy,
+    pub roadkills: KillPolicy,
+    pub environment: KillPolicy,
+}
+
+impl Default for KillPolicies {
+    fn default() -> Self {
+        Self {
+            teamkills: KillPolicy::Enforce,
+            suicides: KillPolicy::Ignore,
+            roadkills: KillPolicy::Ignore,
+            environment: KillPolicy::Ignore,
+        }
+    }
+}
+
+impl KillPolicies {
+    pub fn policy(&self, kind: KillKind) -> KillPolicy {
+        match kind {
+            KillKind::Enemy => KillPolicy::Enforce,
+            KillKind::Teamkill => self.teamkills,
+            KillKind::Suicide => self.suicides,
+            KillKind::Roadkill => self.roadkills,
+            KillKind::Environment => self.environment,
+        }
+    }
+}
+
+/// Configures how players are penalized for breaking the rules of an event.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct PenaltyConfig {
+    /// The steps in the order they are reached, an empty ladder uses the built in one.
+    #[serde(default)]
+    pub ladder: Vec<PenaltyStep>,
+
+    /// Forgive one infraction for every this many minutes a player goes without one.
+    #[serde(default)]
+    pub decay_minutes: Option<u64>,
+
+    #[serde(default)]
+    pub kills: KillPolicies,
+}
+
+impl PenaltyConfig {
+    /// Check the ladder and decay, as [`PenaltyLadder::from_config`] skips what is broken.
+    pub fn validate(&self) -> Result<(), String> {
+        for (i, step) in self.ladder.iter().enumerate() {
+            if let Some(problem) = step.problem() {
+                return Err(format!("Penalty step {} {}", i + 1, problem));
+            }
+        }
+
+        if self.decay_minutes == Some(0) {
+            return Err(
+                "decay_minutes must be at least 1, leave it out to disable decay".to_string(),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// The penalties handed out for the first, second and every further infraction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PenaltyLadder {
+    steps: Vec<PenaltyStep>,
+
+    /// After how much clean play a single infraction is forgiven.
+    decay: Option<Duration>,
+
+    /// Which kills count as infractions.
+    pub kills: KillPolicies,
+}
+
+impl Default for PenaltyLadder {
+    fn default() -> Self {
+        Self {
+            steps: vec![
+                PenaltyStep {
+                    action: PenaltyAction::Punish,
+                    times: 2,
+                },
+                PenaltyStep {
+                    action: PenaltyAction::Kick,
+                    times: 1,
+                },
+            ],
+            decay: None,
+            kills: KillPolicies::default(),
+        }
+    }
+}
+
+impl PenaltyLadder {
+    /// Build the ladder from `config`, falling back to the built in one if it is empty. Steps
+    /// with a [`PenaltyStep::problem`] are skipped.
+    pub fn from_config(config: &PenaltyConfig) -> Self {
+        let steps: Vec<PenaltyStep> = config
+            .ladder
+            .iter()
+            .filter(|step| match step.problem() {
+                Some(problem) => {
+                    warn!("Penalty step {} {} -> Skipping", step.action, problem);
+                    false
+                }
+                None => true,
+            })
+            .cloned()
+            .collect();
+
+        let decay = config
+            .decay_minutes
+            .filter(|minutes| *minutes > 0)
+            .map(|minutes| Duration::from_secs(minutes * 60));
+
+        if steps.is_empty() {
+            return Self {
+                decay,
+                kills: config.kills,
+                ..Default::default()
+            };
+        }
+
+        Self {
+            steps,
+            decay,
+            kills: config.kills,
+        }
+    }
+
+    /// Forgive the infractions earned back by playing clean since the `last` infraction or
+    /// forgiveness. Returns the remaining count and when the clean play for the next
+    /// forgiveness started.
+    pub fn decay(&self, count: u32, last: Instant, now: Instant) -> (u32, Instant) {
+        let Some(decay) = self.decay else {
+            return (count, last);
+        };
+
+        let elapsed = now.saturating_duration_since(last);
+        let forgiven = (elapsed.as_secs() / decay.as_secs()).min(count as u64) as u32;
+        if forgiven == count {
+            return (0, now);
+        }
+
+        (count - forgiven, last + decay * forgiven)
+    }
+
+    /// Get the penalty for the `infraction`th infraction, counting from one.
+    pub fn penalty(&self, infraction: u32) -> &PenaltyAction {
+        let mut reached: u32 = 0;
+        for step in &self.steps {
+            reached = reached.saturating_add(step.times);
+            if infraction <= reached {
+                return &step.action;
+            }
+        }
+
+        // The last step applies to everything beyond the ladder.
+        &self
+            .steps
+            .last()
+            .expect("Penalty ladder is never empty")
+            .action
+    }
+
+    /// The infractions each step covers as the first and, unless it is the last step, the last
+    /// infraction it applies to.
+    pub fn ranges(&self) -> Vec<(u32, Option<u32>, &PenaltyAction)> {
+        let mut first: u32 = 1;
+        let mut ranges = Vec::new();
+
+        for (i, step) in self.steps.iter().enumerate() {
+            let last = (i + 1 < self.steps.len()).then(|| first.saturating_add(step.times - 1));
+            ranges.push((first, last, &step.action));
+            first = first.saturating_add(step.times);
+        }
+
+        ranges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ladder(decay_minutes: Option<u64>) -> PenaltyLadder {
+        PenaltyLadder::from_config(&PenaltyConfig {
+            ladder: vec![
+                PenaltyStep {
+                    action: PenaltyAction::Warn,
+                    times: 2,
+                },
+                PenaltyStep {
+                    action: PenaltyAction::Punish,
+                    times: 3,
+                },
+                PenaltyStep {
+                    action: PenaltyAction::TempBan { hours: 2 },
+                    times: 1,
+                },
+            ],
+            decay_minutes,
+            kills: KillPolicies::default(),
+        })
+    }
+
+    #[test]
+    fn penalty_follows_step_boundaries() {
+        let ladder = ladder(None);
+
+        assert_eq!(ladder.penalty(1), &PenaltyAction::Warn);
+        assert_eq!(ladder.penalty(2), &PenaltyAction::Warn);
+        assert_eq!(ladder.penalty(3), &PenaltyAction::Punish);
+        assert_eq!(ladder.penalty(5), &PenaltyAction::Punish);
+        assert_eq!(ladder.penalty(6), &PenaltyAction::TempBan { hours: 2 });
+    }
+
+    #[test]
+    fn last_step_applies_beyond_ladder() {
+        let ladder = ladder(None);
+
+        assert_eq!(ladder.penalty(7), &PenaltyAction::TempBan { hours: 2 });
+        assert_eq!(
+            ladder.penalty(u32::MAX),
+            &PenaltyAction::TempBan { hours: 2 }
+        );
+    }
+
+    #[test]
+    fn ranges_cover_every_step() {
+        let ladder = ladder(None);
+
+        assert_eq!(
+            ladder.ranges(),
+            vec![
+                (1, Some(2), &PenaltyAction::Warn),
+                (3, Some(5), &PenaltyAction::Punish),
+                (6, None, &PenaltyAction::TempBan { hours: 2 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn huge_steps_saturate() {
+        let ladder = PenaltyLadder::from_config(&PenaltyConfig {
+            ladder: vec![
+                PenaltyStep {
+                    action: PenaltyAction::Warn,
+                    times: u32::MAX,
+                },
+                PenaltyStep {
+                    action: PenaltyAction::Kick,
+                    times: u32::MAX,
+                },
+            ],
+            ..Default::default()
+        });
+
+        assert_eq!(ladder.penalty(u32::MAX), &PenaltyAction::Warn);
+        assert_eq!(ladder.ranges()[1].0, u32::MAX);
+    }
+
+    #[test]
+    fn invalid_steps_are_skipped() {
+        let ladder = PenaltyLadder::from_config(&PenaltyConfig {
+            ladder: vec![
+                PenaltyStep {
+                    action: PenaltyAction::Warn,
+                    times: 0,
+                },
+                PenaltyStep {
+                    action: PenaltyAction::TempBan { hours: 0 },
+                    times: 1,
+                },
+            ],
+            ..Default::default()
+        });
+
+        assert_eq!(ladder, PenaltyLadder::default());
+    }
+
+    #[test]
+    fn decay_without_interval_keeps_count() {
+        let ladder = ladder(None);
+        let last = Instant::now();
+
+        assert_eq!(
+            ladder.decay(3, last, last + Duration::from_secs(24 * 60 * 60)),
+            (3, last)
+        );
+    }
+
+    #[test]
+    fn decay_forgives_whole_intervals() {
+        let ladder = ladder(Some(10));
+        let interval = Duration::from_secs(10 * 60);
+        let last = Instant::now();
+
+        // Just short of an interval forgives nothing.
+        let now = last + interval - Duration::from_secs(1);
+        assert_eq!(ladder.decay(3, last, now), (3, last));
+
+        // Exactly two intervals forgive two and the next one starts right away.
+        let now = last + interval * 2;
+        assert_eq!(ladder.decay(3, last, now), (1, last + interval * 2));
+
+        // Partial progress towards the next forgiveness is kept.
+        let now = last + interval * 2 + Duration::from_secs(30);
+        assert_eq!(ladder.decay(3, last, now), (1, last + interval * 2));
+    }
+
+    #[test]
+    fn decay_of_every_infraction_restarts_clean_play() {
+        let ladder = ladder(Some(10));
+        let interval = Duration::from_secs(10 * 60);
+        let last = Instant::now();
+
+        let now = last + interval * 3;
+        assert_eq!(ladder.decay(3, last, now), (0, now));
+
+        let now = last + interval * 10;
+        assert_eq!(ladder.decay(3, last, now), (0, now));
+    }
+
+    #[test]
+    fn validate_reports_broken_config() {
+        let broken_step = PenaltyConfig {
+            ladder: vec![PenaltyStep {
+                action: PenaltyAction::TempBan { hours: 0 },
+                times: 1,
+            }],
+            ..Default::default()
+        };
+        assert!(broken_step.validate().is_err());
+
+        let broken_decay = PenaltyConfig {
+            decay_minutes: Some(0),
+            ..Default::default()
+        };
+        assert!(broken_decay.validate().is_err());
+
+        assert!(PenaltyConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn every_kill_kind_has_its_own_policy() {
+        let policies = KillPolicies {
+            teamkills: KillPolicy::Enforce,
+            suicides: KillPolicy::Ignore,
+            roadkills: KillPolicy::Penalize,
+            environment: KillPolicy::Ignore,
+        };
+
+        assert_eq!(policies.policy(KillKind::Enemy), KillPolicy::Enforce);
+        assert_eq!(policies.policy(KillKind::Teamkill), KillPolicy::Enforce);
+        assert_eq!(policies.policy(KillKind::Suicide), KillPolicy::Ignore);
+        assert_eq!(policies.policy(KillKind::Roadkill), KillPolicy::Penalize);
+        assert_eq!(policies.policy(KillKind::Environment), KillPolicy::Ignore);
+    }
+}
@@ -1,30 +1,34 @@
 use futures_util::future::join_all;
+use log::warn;
 use wise_api::rcon::parsing::Player;
 
-use crate::client::{WsTransceiver, WsTransceiverExt};
+use crate::client::{ClientError, WsTransceiver, WsTransceiverExt};
 
-pub async fn get_players_with_team(transceiver: &mut WsTransceiver) -> Vec<(Player, String)> {
+pub async fn get_players_with_team(
+    transceiver: &mut WsTransceiver,
+) -> Result<Vec<(Player, String)>, ClientError> {
     // Get all players
     // For each player request the player info
-    let players = transceiver.get_players().await.unwrap();
+    let players = transceiver.get_players().await?;
 
     let player_infos = join_all(players.iter().map(|player| {
         let mut clone = transceiver.clone();
-        async move {
-            clone.get_playerinfo(player.name.clone()).await
-        }
+        async move { clone.get_playerinfo(player.name.clone()).await }
     }))
     .await;
 
-    players
+    let players_with_team = players
         .into_iter()
         .zip(player_infos.into_iter())
-        .filter_map(|(player, player_info)| {
-            if let Some(player_info) = player_info {
-                Some((player, player_info.team))
-            } else {
+        .filter_map(|(player, player_info)| match player_info {
+            Ok(Some(player_info)) => Some((player, player_info.team)),
+            Ok(None) => None,
+            Err(e) => {
+                warn!("Failed to get player info for {:?}: {}", player, e);
                 None
             }
         })
-        .collect()
+        .collect();
+
+    Ok(players_with_team)
 }
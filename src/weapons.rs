@@ -0,0 +1,458 @@
+use std::{collections::HashMap, fmt::Display};
+
+use clap::ValueEnum;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use WeaponCategory::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum WeaponCategory {
+    Melee,
+    Pistol,
+    BoltAction,
+    SemiAuto,
+    AssaultRifle,
+    Smg,
+    Shotgun,
+    #[serde(rename = "mg")]
+    #[value(name = "mg")]
+    MachineGun,
+    Sniper,
+    Flamethrower,
+    #[serde(rename = "at")]
+    #[value(name = "at")]
+    AntiTank,
+    TankGun,
+    Artillery,
+    Explosive,
+    /// Anything mounted on or being a vehicle, including roadkills.
+    Vehicle,
+    /// Kills without a weapon, such as falling or drowning.
+    Environment,
+}
+
+impl Display for WeaponCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Melee => "Melee",
+            Pistol => "Pistol",
+            BoltAction => "Bolt-Action",
+            SemiAuto => "Semi-Auto",
+            AssaultRifle => "Assault Rifle",
+            Smg => "SMG",
+            Shotgun => "Shotgun",
+            MachineGun => "MG",
+            Sniper => "Sniper",
+            Flamethrower => "Flamethrower",
+            AntiTank => "AT",
+            TankGun => "Tank Gun",
+            Artillery => "Artillery",
+            Explosive => "Explosive",
+            Vehicle => "Vehicle",
+            Environment => "Environment",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Which weapons may be used during a restricted weapon event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WeaponRule {
+    /// Only weapons of these categories are allowed.
+    Allow(Vec<WeaponCategory>),
+
+    /// Weapons of these categories are forbidden, everything else is allowed.
+    Deny(Vec<WeaponCategory>),
+}
+
+impl WeaponRule {
+    /// Whether a kill with `weapon` is within the rule. Unknown weapons are only permitted
+    /// when the rule is a deny list.
+    pub fn permits(&self, catalogue: &WeaponCatalogue, weapon: &str) -> bool {
+        let categories = catalogue.categories(weapon);
+
+        match self {
+            WeaponRule::Allow(allowed) => categories.iter().any(|c| allowed.contains(c)),
+            WeaponRule::Deny(denied) => !categories.iter().any(|c| denied.contains(c)),
+        }
+    }
+
+    /// A sentence explaining the rule to players.
+    pub fn description(&self) -> String {
+        match self {
+            WeaponRule::Allow(allowed) => {
+                format!("Only {} weapons are allowed.", join_categories(allowed))
+            }
+            WeaponRule::Deny(denied) => {
+                format!("{} weapons are forbidden.", join_categories(denied))
+            }
+        }
+    }
+}
+
+impl Display for WeaponRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WeaponRule::Allow(allowed) => write!(f, "{} only", join_categories(allowed)),
+            WeaponRule::Deny(denied) => write!(f, "no {}", join_categories(denied)),
+        }
+    }
+}
+
+/// Join categories for humans, as in "Pistol, SMG and Melee".
+fn join_categories(categories: &[WeaponCategory]) -> String {
+    let names: Vec<String> = categories.iter().map(|c| c.to_string()).collect();
+
+    match names.split_last() {
+        None => String::new(),
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+    }
+}
+
+/// A weapon name or keyword with the categories it belongs to.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WeaponEntry {
+    pub name: String,
+    pub categories: Vec<WeaponCategory>,
+}
+
+impl WeaponEntry {
+    /// Why the entry cannot be part of the catalogue, if it cannot.
+    pub fn problem(&self) -> Option<&'static str> {
+        if self.name.trim().is_empty() {
+            // An empty keyword would match every weapon.
+            return Some("has no name");
+        }
+
+        if self.categories.is_empty() {
+            return Some("has no categories");
+        }
+
+        None
+    }
+}
+
+/// Additions and overrides to the built in weapon catalogue.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct WeaponConfig {
+    /// Weapons matched by their exact log name.
+    #[serde(default)]
+    pub weapons: Vec<WeaponEntry>,
+
+    /// Weapons matched by a part of their log name, used for weapons without an exact entry.
+    #[serde(default)]
+    pub keywords: Vec<WeaponEntry>,
+}
+
+impl WeaponConfig {
+    /// Check every entry, as [`WeaponCatalogue::from_config`] skips the broken ones.
+    pub fn validate(&self) -> Result<(), String> {
+        for entry in self.weapons.iter().chain(&self.keywords) {
+            if let Some(problem) = entry.problem() {
+                return Err(format!("Weapon \"{}\" {}", entry.name, problem));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Maps weapon log names to the categories they belong to.
+#[derive(Debug, Clone)]
+pub struct WeaponCatalogue {
+    /// Lowercased exact names.
+    names: HashMap<String, Vec<WeaponCategory>>,
+
+    /// Lowercased keywords.
+    keywords: Vec<(String, Vec<WeaponCategory>)>,
+}
+
+impl Default for WeaponCatalogue {
+    fn default() -> Self {
+        let to_owned = |(name, categories): &(&str, &[WeaponCategory])| {
+            (name.to_lowercase(), categories.to_vec())
+        };
+
+        Self {
+            names: BUILTIN_WEAPONS.iter().map(to_owned).collect(),
+            keywords: BUILTIN_KEYWORDS.iter().map(to_owned).collect(),
+        }
+    }
+}
+
+impl WeaponCatalogue {
+    /// Build the built in catalogue with the entries from `config` taking precedence. Entries
+    /// with a [`WeaponEntry::problem`] are skipped.
+    pub fn from_config(config: &WeaponConfig) -> Self {
+        let mut catalogue = Self::default();
+        let usable = |entry: &&WeaponEntry| match entry.problem() {
+            Some(problem) => {
+                warn!("Weapon \"{}\" {} -> Skipping", entry.name, problem);
+                false
+            }
+            None => true,
+        };
+
+        for entry in config.weapons.iter().filter(usable) {
+            catalogue
+                .names
+                .insert(entry.name.to_lowercase(), entry.categories.clone());
+        }
+
+        for entry in config.keywords.iter().filter(usable) {
+            let keyword = entry.name.to_lowercase();
+            catalogue
+                .keywords
+                .retain(|(existing, _)| *existing != keyword);
+            // Configured keywords are checked first.
+            catalogue
+                .keywords
+                .insert(0, (keyword, entry.categories.clone()));
+        }
+
+        catalogue
+    }
+
+    /// Get the categories of a weapon. Exact names win over keywords, if there is no exact
+    /// entry the categories of all matching keywords are combined.
+    pub fn categories(&self, weapon: &str) -> Vec<WeaponCategory> {
+        let lower = weapon.to_lowercase();
+        if let Some(categories) = self.names.get(&lower) {
+            return categories.clone();
+        }
+
+        let mut categories = Vec::new();
+        for (keyword, keyword_categories) in &self.keywords {
+            if !lower.contains(keyword.as_str()) {
+                continue;
+            }
+
+            for category in keyword_categories {
+                if !categories.contains(category) {
+                    categories.push(*category);
+                }
+            }
+        }
+
+        categories
+    }
+}
+
+/// Weapons by the name they appear with in kill logs.
+const BUILTIN_WEAPONS: &[(&str, &[WeaponCategory])] = &[
+    ("UNKNOWN", &[Environment]),
+    // United States
+    ("M1 GARAND", &[SemiAuto]),
+    ("M1 CARBINE", &[SemiAuto]),
+    ("M1A1 THOMPSON", &[Smg]),
+    ("M3 GREASE GUN", &[Smg]),
+    ("M1918A2 BAR", &[AssaultRifle]),
+    ("M97 TRENCH GUN", &[Shotgun]),
+    ("M1919 BROWNING", &[MachineGun]),
+    ("M1903 SPRINGFIELD", &[BoltAction, Sniper]),
+    ("COLT M1911", &[Pistol]),
+    ("M3 KNIFE", &[Melee]),
+    ("BAZOOKA", &[AntiTank]),
+    ("M2 FLAMETHROWER", &[Flamethrower]),
+    ("MK2 GRENADE", &[Explosive]),
+    ("M2 AP MINE", &[Explosive]),
+    ("M1A1 AT MINE", &[Explosive, AntiTank]),
+    ("57MM CANNON [M1 57mm]", &[AntiTank]),
+    ("155MM HOWITZER [M114]", &[Artillery]),
+    // Germany
+    ("KARABINER 98K", &[BoltAction]),
+    ("KARABINER 98K x8", &[BoltAction, Sniper]),
+    ("GEWEHR 43", &[SemiAuto]),
+    ("GEWEHR 43 x4", &[SemiAuto, Sniper]),
+    ("STG44", &[AssaultRifle]),
+    ("FG42", &[AssaultRifle]),
+    ("FG42 x4", &[AssaultRifle, Sniper]),
+    ("MP40", &[Smg]),
+    ("MG34", &[MachineGun]),
+    ("MG42", &[MachineGun]),
+    ("WALTHER P38", &[Pistol]),
+    ("LUGER P08", &[Pistol]),
+    ("FELDSPATEN", &[Melee]),
+    ("PANZERSCHRECK", &[AntiTank]),
+    ("FLAMMENWERFER 41", &[Flamethrower]),
+    ("M24 STIELHANDGRANATE", &[Explosive]),
+    ("M43 STIELHANDGRANATE", &[Explosive]),
+    ("S-MINE", &[Explosive]),
+    ("TELLERMINE 43", &[Explosive, AntiTank]),
+    ("75MM CANNON [PAK 40]", &[AntiTank]),
+    ("150MM HOWITZER [sFH 18]", &[Artillery]),
+    // Soviet Union
+    ("MOSIN NAGANT 1891", &[BoltAction]),
+    ("MOSIN NAGANT 91/30", &[BoltAction]),
+    ("MOSIN NAGANT M38", &[BoltAction]),
+    ("SCOPED MOSIN NAGANT 91/30", &[BoltAction, Sniper]),
+    ("SVT40", &[SemiAuto]),
+    ("SCOPED SVT40", &[SemiAuto, Sniper]),
+    ("PPSH 41", &[Smg]),
+    ("PPSH 41 W/DRUM", &[Smg]),
+    ("DP-27", &[MachineGun]),
+    ("NAGANT M1895", &[Pistol]),
+    ("TOKAREV TT33", &[Pistol]),
+    ("MPL-50 SPADE", &[Melee]),
+    ("PTRS-41", &[AntiTank]),
+    ("RG-42 GRENADE", &[Explosive]),
+    ("MOLOTOV", &[Explosive]),
+    ("POMZ AP MINE", &[Explosive]),
+    ("TM-35 AT MINE", &[Explosive, AntiTank]),
+    ("57MM CANNON [ZiS-2]", &[AntiTank]),
+    ("122MM HOWITZER [M1938 (M-30)]", &[Artillery]),
+    // Great Britain
+    ("SMLE No.1 Mk III", &[BoltAction]),
+    ("Rifle No.4 Mk I", &[BoltAction]),
+    ("Rifle No.4 Mk I Sniper", &[BoltAction, Sniper]),
+    ("Rifle No.5 Mk I", &[BoltAction]),
+    ("Sten Gun", &[Smg]),
+    ("Sten Gun Mk V", &[Smg]),
+    ("Lanchester", &[Smg]),
+    ("M1928A1 THOMPSON", &[Smg]),
+    ("Bren Gun", &[MachineGun]),
+    ("Lewis Gun", &[MachineGun]),
+    ("Webley MK VI", &[Pistol]),
+    ("Fairbairn–Sykes", &[Melee]),
+    ("PIAT", &[AntiTank]),
+    ("Boys Anti-tank Rifle", &[AntiTank]),
+    ("FLAMETHROWER", &[Flamethrower]),
+    ("Mills Bomb", &[Explosive]),
+    ("No.82 Grenade", &[Explosive, AntiTank]),
+    ("A.P. Shrapnel Mine Mk II", &[Explosive]),
+    ("A.T. Mine G.S. Mk V", &[Explosive, AntiTank]),
+    ("QF 6-POUNDER [QF 6-Pounder]", &[AntiTank]),
+    ("QF 25-POUNDER [QF 25-Pounder]", &[Artillery]),
+    // Shared
+    ("SATCHEL", &[Explosive]),
+    ("SATCHEL CHARGE", &[Explosive]),
+    ("BOMBING RUN", &[Artillery]),
+    ("STRAFING RUN", &[Artillery]),
+    ("PRECISION STRIKE", &[Artillery]),
+    ("Katyusha Barrage", &[Artillery]),
+];
+
+/// Parts of weapon names for the many vehicle weapons and anything missing above.
+const BUILTIN_KEYWORDS: &[(&str, &[WeaponCategory])] = &[
+    ("knife", &[Melee]),
+    ("shovel", &[Melee]),
+    ("spaten", &[Melee]),
+    ("spade", &[Melee]),
+    ("sykes", &[Melee]),
+    ("howitzer", &[Artillery]),
+    ("cannon [", &[TankGun, Vehicle]),
+    ("coaxial", &[MachineGun, Vehicle]),
+    ("hull ", &[MachineGun, Vehicle]),
+    ("m2 browning [", &[MachineGun, Vehicle]),
+    ("mg42 [", &[MachineGun, Vehicle]),
+    ("jeep", &[Vehicle]),
+    ("half-track", &[Vehicle]),
+    ("kubelwagen", &[Vehicle]),
+    ("transport", &[Vehicle]),
+    ("supply", &[Vehicle]),
+    ("sd.kfz", &[Vehicle]),
+    ("sherman", &[Vehicle]),
+    ("stuart", &[Vehicle]),
+    ("tiger", &[Vehicle]),
+    ("panther", &[Vehicle]),
+    ("luchs", &[Vehicle]),
+    ("puma", &[Vehicle]),
+    ("greyhound", &[Vehicle]),
+    ("t-34", &[Vehicle]),
+    ("is-1", &[Vehicle]),
+    ("t70", &[Vehicle]),
+    ("ba-10", &[Vehicle]),
+    ("cromwell", &[Vehicle]),
+    ("churchill", &[Vehicle]),
+    ("firefly", &[Vehicle]),
+    ("tetrarch", &[Vehicle]),
+    ("daimler", &[Vehicle]),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_name_beats_keyword() {
+        let catalogue = WeaponCatalogue::default();
+
+        // Would match the "cannon [" keyword of the tank guns.
+        assert_eq!(
+            catalogue.categories("57MM CANNON [M1 57mm]"),
+            vec![AntiTank]
+        );
+        assert_eq!(catalogue.categories("m1 garand"), vec![SemiAuto]);
+    }
+
+    #[test]
+    fn keywords_combine_categories() {
+        let catalogue = WeaponCatalogue::default();
+
+        assert_eq!(
+            catalogue.categories("75MM CANNON [Sherman M4A3(75)W]"),
+            vec![TankGun, Vehicle]
+        );
+        assert_eq!(catalogue.categories("Trench Knife"), vec![Melee]);
+        assert!(catalogue.categories("Something New").is_empty());
+    }
+
+    #[test]
+    fn config_overrides_builtin() {
+        let config = WeaponConfig {
+            weapons: vec![WeaponEntry {
+                name: "m1 garand".to_string(),
+                categories: vec![Sniper],
+            }],
+            keywords: vec![WeaponEntry {
+                name: "Sherman".to_string(),
+                categories: vec![Artillery],
+            }],
+        };
+        let catalogue = WeaponCatalogue::from_config(&config);
+
+        assert_eq!(catalogue.categories("M1 GARAND"), vec![Sniper]);
+        assert_eq!(catalogue.categories("SHERMAN M4A3E2"), vec![Artillery]);
+    }
+
+    #[test]
+    fn allow_rule_permits_listed_categories() {
+        let catalogue = WeaponCatalogue::default();
+        let rule = WeaponRule::Allow(vec![Melee, Pistol]);
+
+        assert!(rule.permits(&catalogue, "FELDSPATEN"));
+        assert!(rule.permits(&catalogue, "COLT M1911"));
+        assert!(!rule.permits(&catalogue, "MP40"));
+        assert!(!rule.permits(&catalogue, "Something New"));
+    }
+
+    #[test]
+    fn deny_rule_forbids_listed_categories() {
+        let catalogue = WeaponCatalogue::default();
+        let rule = WeaponRule::Deny(vec![Sniper]);
+
+        assert!(!rule.permits(&catalogue, "KARABINER 98K x8"));
+        assert!(rule.permits(&catalogue, "KARABINER 98K"));
+        assert!(rule.permits(&catalogue, "Something New"));
+    }
+
+    #[test]
+    fn broken_entries_are_reported_and_skipped() {
+        let config = WeaponConfig {
+            weapons: Vec::new(),
+            keywords: vec![WeaponEntry {
+                name: " ".to_string(),
+                categories: vec![Melee],
+            }],
+        };
+
+        assert!(config.validate().is_err());
+
+        // The empty keyword would have made every weapon a melee weapon.
+        let catalogue = WeaponCatalogue::from_config(&config);
+        assert_eq!(catalogue.categories("MP40"), vec![Smg]);
+    }
+}